@@ -2,10 +2,20 @@ use byteorder::LittleEndian;
 use byteorder::ReadBytesExt;
 use std::collections::HashMap;
 use std::io::Read;
-use std::path::PathBuf;
-use std::str::FromStr;
+use std::path::Path;
 
-const API_SET_SCHEMA_DLL_PATH: &str = "C:/Windows/System32/apisetschema.dll";
+pub const API_SET_SCHEMA_DLL_NAME: &str = "apisetschema.dll";
+
+/* Partial snapshot of the Windows 10 API Set schema covering the most commonly imported
+contracts, used when no apisetschema.dll is available (e.g. when running on Linux) */
+const EMBEDDED_API_SET_SCHEMA: &str = include_str!("apiset_snapshot.json");
+
+/* Flags of the namespace entries (v4 and v6) */
+pub const API_SET_SCHEMA_ENTRY_FLAGS_SEALED: u32 = 0x1;
+pub const API_SET_SCHEMA_ENTRY_FLAGS_EXTENSION: u32 = 0x2;
+
+/* Hash multiplier of the v6 schemas shipped with Windows */
+const DEFAULT_HASH_MULTIPLIER: u32 = 0x1F;
 
 #[derive(Default, Clone, Debug)]
 #[repr(C)]
@@ -25,7 +35,7 @@ impl APISetNamespace {
     }
 
     pub fn from_parser(
-        cursor: &mut std::io::Cursor<&Vec<u8>>,
+        cursor: &mut std::io::Cursor<&[u8]>,
     ) -> Result<APISetNamespace, Box<dyn std::error::Error>> {
         let mut asn = APISetNamespace::new();
 
@@ -58,7 +68,7 @@ impl APISetNamespaceEntry {
     }
 
     pub fn from_parser(
-        cursor: &mut std::io::Cursor<&Vec<u8>>,
+        cursor: &mut std::io::Cursor<&[u8]>,
     ) -> Result<APISetNamespaceEntry, Box<dyn std::error::Error>> {
         let mut asne = APISetNamespaceEntry::new();
 
@@ -89,7 +99,7 @@ impl APISetValueEntry {
     }
 
     pub fn from_parser(
-        cursor: &mut std::io::Cursor<&Vec<u8>>,
+        cursor: &mut std::io::Cursor<&[u8]>,
     ) -> Result<APISetValueEntry, Box<dyn std::error::Error>> {
         let mut asve = APISetValueEntry::new();
 
@@ -103,9 +113,283 @@ impl APISetValueEntry {
     }
 }
 
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct APISetNamespaceV2 {
+    version: u32,
+    count: u32,
+}
+
+impl APISetNamespaceV2 {
+    pub fn new() -> APISetNamespaceV2 {
+        return APISetNamespaceV2::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut std::io::Cursor<&[u8]>,
+    ) -> Result<APISetNamespaceV2, Box<dyn std::error::Error>> {
+        let mut asn = APISetNamespaceV2::new();
+
+        asn.version = cursor.read_u32::<LittleEndian>()?;
+        asn.count = cursor.read_u32::<LittleEndian>()?;
+
+        return Ok(asn);
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct APISetNamespaceEntryV2 {
+    name_offset: u32,
+    name_length: u32,
+    data_offset: u32,
+}
+
+impl APISetNamespaceEntryV2 {
+    pub fn new() -> APISetNamespaceEntryV2 {
+        return APISetNamespaceEntryV2::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut std::io::Cursor<&[u8]>,
+    ) -> Result<APISetNamespaceEntryV2, Box<dyn std::error::Error>> {
+        let mut asne = APISetNamespaceEntryV2::new();
+
+        asne.name_offset = cursor.read_u32::<LittleEndian>()?;
+        asne.name_length = cursor.read_u32::<LittleEndian>()?;
+        asne.data_offset = cursor.read_u32::<LittleEndian>()?;
+
+        return Ok(asne);
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct APISetValueEntryV2 {
+    name_offset: u32,
+    name_length: u32,
+    value_offset: u32,
+    value_length: u32,
+}
+
+impl APISetValueEntryV2 {
+    pub fn new() -> APISetValueEntryV2 {
+        return APISetValueEntryV2::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut std::io::Cursor<&[u8]>,
+    ) -> Result<APISetValueEntryV2, Box<dyn std::error::Error>> {
+        let mut asve = APISetValueEntryV2::new();
+
+        asve.name_offset = cursor.read_u32::<LittleEndian>()?;
+        asve.name_length = cursor.read_u32::<LittleEndian>()?;
+        asve.value_offset = cursor.read_u32::<LittleEndian>()?;
+        asve.value_length = cursor.read_u32::<LittleEndian>()?;
+
+        return Ok(asve);
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct APISetNamespaceV4 {
+    version: u32,
+    size: u32,
+    flags: u32,
+    count: u32,
+}
+
+impl APISetNamespaceV4 {
+    pub fn new() -> APISetNamespaceV4 {
+        return APISetNamespaceV4::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut std::io::Cursor<&[u8]>,
+    ) -> Result<APISetNamespaceV4, Box<dyn std::error::Error>> {
+        let mut asn = APISetNamespaceV4::new();
+
+        asn.version = cursor.read_u32::<LittleEndian>()?;
+        asn.size = cursor.read_u32::<LittleEndian>()?;
+        asn.flags = cursor.read_u32::<LittleEndian>()?;
+        asn.count = cursor.read_u32::<LittleEndian>()?;
+
+        return Ok(asn);
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct APISetNamespaceEntryV4 {
+    flags: u32,
+    name_offset: u32,
+    name_length: u32,
+    alias_offset: u32,
+    alias_length: u32,
+    data_offset: u32,
+}
+
+impl APISetNamespaceEntryV4 {
+    pub fn new() -> APISetNamespaceEntryV4 {
+        return APISetNamespaceEntryV4::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut std::io::Cursor<&[u8]>,
+    ) -> Result<APISetNamespaceEntryV4, Box<dyn std::error::Error>> {
+        let mut asne = APISetNamespaceEntryV4::new();
+
+        asne.flags = cursor.read_u32::<LittleEndian>()?;
+        asne.name_offset = cursor.read_u32::<LittleEndian>()?;
+        asne.name_length = cursor.read_u32::<LittleEndian>()?;
+        asne.alias_offset = cursor.read_u32::<LittleEndian>()?;
+        asne.alias_length = cursor.read_u32::<LittleEndian>()?;
+        asne.data_offset = cursor.read_u32::<LittleEndian>()?;
+
+        return Ok(asne);
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct APISetHashEntry {
+    hash: u32,
+    index: u32,
+}
+
+impl APISetHashEntry {
+    pub fn new() -> APISetHashEntry {
+        return APISetHashEntry::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut std::io::Cursor<&[u8]>,
+    ) -> Result<APISetHashEntry, Box<dyn std::error::Error>> {
+        let mut ashe = APISetHashEntry::new();
+
+        ashe.hash = cursor.read_u32::<LittleEndian>()?;
+        ashe.index = cursor.read_u32::<LittleEndian>()?;
+
+        return Ok(ashe);
+    }
+}
+
+/* Host dll of a contract. The importer is empty for the default host */
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct APISetHost {
+    pub importer: String,
+    pub host: String,
+}
+
+impl APISetHost {
+    pub fn new() -> APISetHost {
+        return APISetHost::default();
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct APISetContract {
+    pub flags: u32,
+    /* Number of characters of the name used for lookups, the name without its last "-N" revision */
+    pub hashed_length: usize,
+    pub hosts: Vec<APISetHost>,
+}
+
+impl APISetContract {
+    pub fn new() -> APISetContract {
+        return APISetContract::default();
+    }
+
+    /*
+     * Picks the host the loader would use: an importer listed in the exceptions of the contract gets
+     * its own host, every other importer gets the first (default) host
+     */
+    pub fn get_host(&self, importer: Option<&str>) -> Option<&str> {
+        let exception = importer.and_then(|importer| {
+            self.hosts
+                .iter()
+                .skip(1)
+                .find(|host| host.importer.eq_ignore_ascii_case(importer))
+        });
+
+        let host = exception.or(self.hosts.first())?;
+
+        if host.host.is_empty() {
+            return None;
+        }
+
+        return Some(&host.host);
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        return self.flags & API_SET_SCHEMA_ENTRY_FLAGS_SEALED != 0;
+    }
+
+    pub fn is_extension(&self) -> bool {
+        return self.flags & API_SET_SCHEMA_ENTRY_FLAGS_EXTENSION != 0;
+    }
+
+    /* Same layout as the contracts read by APISet::from_json, so dumps can be loaded back */
+    pub fn to_json(&self) -> json::JsonValue {
+        let hosts: Vec<json::JsonValue> = self
+            .hosts
+            .iter()
+            .map(|host| {
+                if host.importer.is_empty() {
+                    return json::JsonValue::from(host.host.as_str());
+                }
+
+                return json::object! {
+                    importer: host.importer.as_str(),
+                    host: host.host.as_str(),
+                };
+            })
+            .collect();
+
+        return json::object! {
+            flags: self.flags,
+            sealed: self.is_sealed(),
+            extension: self.is_extension(),
+            hosts: hosts,
+        };
+    }
+
+    /* Hosts on one line, exceptions being followed by their importer */
+    pub fn hosts_to_string(&self) -> String {
+        return self
+            .hosts
+            .iter()
+            .map(|host| {
+                if host.importer.is_empty() {
+                    return host.host.clone();
+                }
+
+                return format!("{} (for {})", host.host, host.importer);
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+    }
+}
+
+impl PartialEq for APISetContract {
+    fn eq(&self, other: &Self) -> bool {
+        return self.flags == other.flags && self.hosts == other.hosts;
+    }
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct APISet {
-    mapping: HashMap<String, String>,
+    version: u32,
+    contracts: HashMap<String, APISetContract>,
+    /* Sorted by hash, with the name of the hashed contract. Empty for schemas before v6 */
+    hash_table: Vec<(u32, String)>,
+    hash_multiplier: u32,
+    /*
+     * Read from an apisetschema.dll, so the schema lists every contract of the system. The embedded
+     * snapshot and json mappings can be partial
+     */
+    complete: bool,
 }
 
 impl APISet {
@@ -113,102 +397,640 @@ impl APISet {
         return APISet::default();
     }
 
-    pub fn map(&self, dll_name: &str) -> Option<String> {
-        if dll_name.ends_with(".dll") {
-            return self
-                .mapping
-                .get(dll_name.split_at_checked(dll_name.len() - 4).unwrap().0)
-                .cloned();
+    pub fn get_version(&self) -> u32 {
+        return self.version;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.contracts.is_empty();
+    }
+
+    pub fn is_complete(&self) -> bool {
+        return self.complete;
+    }
+
+    /* Contracts sorted by name */
+    pub fn get_contracts(&self) -> Vec<(&String, &APISetContract)> {
+        let mut contracts: Vec<(&String, &APISetContract)> = self.contracts.iter().collect();
+
+        contracts.sort_by_key(|(name, _)| *name);
+
+        return contracts;
+    }
+
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut contracts = json::JsonValue::new_object();
+
+        for (name, contract) in self.get_contracts() {
+            contracts[name.as_str()] = contract.to_json();
+        }
+
+        return json::object! {
+            version: self.version,
+            contracts: contracts,
+        };
+    }
+
+    pub fn to_table(&self) -> String {
+        let contracts = self.get_contracts();
+
+        let name_width = contracts
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0)
+            .max("CONTRACT".len());
+
+        let mut table = format!("{:name_width$}  {:9}  HOSTS\n", "CONTRACT", "FLAGS");
+
+        for (name, contract) in contracts {
+            let mut flags: Vec<&str> = Vec::new();
+
+            if contract.is_sealed() {
+                flags.push("sealed");
+            }
+
+            if contract.is_extension() {
+                flags.push("ext");
+            }
+
+            table.push_str(&format!(
+                "{:name_width$}  {:9}  {}\n",
+                name,
+                flags.join(","),
+                contract.hosts_to_string()
+            ));
+        }
+
+        return table;
+    }
+
+    /*
+     * Rebuilds the hash table of the contracts, for v6 schemas that do not come with one (json
+     * serialized mappings). These can list several revisions of a contract, while a schema only
+     * holds one per hashed name, so only the highest revision is kept
+     */
+    fn build_hash_table(&mut self) {
+        self.hash_multiplier = DEFAULT_HASH_MULTIPLIER;
+
+        let mut latest_revisions: HashMap<String, (u32, String)> = HashMap::new();
+
+        for (name, contract) in self.contracts.iter_mut() {
+            contract.hashed_length = name.rfind('-').unwrap_or(name.len());
+
+            let hashed_name = &name[..contract.hashed_length];
+            let revision = name
+                .get(contract.hashed_length + 1..)
+                .and_then(|revision| revision.parse::<u32>().ok())
+                .unwrap_or(0);
+
+            match latest_revisions.get(hashed_name) {
+                Some((latest_revision, _)) if *latest_revision >= revision => {}
+                _ => {
+                    latest_revisions.insert(hashed_name.to_string(), (revision, name.clone()));
+                }
+            }
+        }
+
+        let mut hash_table: Vec<(u32, String)> = latest_revisions
+            .into_iter()
+            .map(|(hashed_name, (_, name))| {
+                (
+                    hash_api_set_name(&hashed_name, DEFAULT_HASH_MULTIPLIER),
+                    name,
+                )
+            })
+            .collect();
+
+        hash_table.sort();
+
+        self.hash_table = hash_table;
+    }
+
+    /*
+     * Looks up a contract the way the loader does. From v6, the name is truncated before its last
+     * "-N" revision and searched in the hash table, so any revision of a contract matches the one
+     * listed in the schema. Older schemas are searched by exact name
+     */
+    pub fn get_contract(&self, dll_name: &str) -> Option<&APISetContract> {
+        let dll_name = dll_name.to_ascii_lowercase();
+        let dll_name = dll_name.strip_suffix(".dll").unwrap_or(&dll_name);
+
+        if self.hash_table.is_empty() {
+            return self.contracts.get(dll_name);
         }
 
-        return self.mapping.get(dll_name).cloned();
+        let hashed_name = &dll_name[..dll_name.rfind('-')?];
+        let hash = hash_api_set_name(hashed_name, self.hash_multiplier);
+
+        let index = self
+            .hash_table
+            .binary_search_by_key(&hash, |(entry_hash, _)| *entry_hash)
+            .ok()?;
+
+        let contract_name = &self.hash_table[index].1;
+        let contract = self.contracts.get(contract_name)?;
+
+        if contract_name.get(..contract.hashed_length)? != hashed_name {
+            return None;
+        }
+
+        return Some(contract);
+    }
+
+    pub fn map(&self, dll_name: &str, importer: Option<&str>) -> Option<String> {
+        return self
+            .get_contract(dll_name)?
+            .get_host(importer)
+            .map(String::from);
+    }
+
+    /*
+     * Loads a mapping serialized as { "contracts": { "api-ms-win-...": <contract>, ... } }, where a
+     * contract is either the name of its host dll, or
+     * { "flags": 0, "hosts": [ "host.dll", { "importer": "kernel32.dll", "host": "other.dll" } ] }
+     */
+    pub fn from_json(json_str: &str) -> Result<APISet, Box<dyn std::error::Error>> {
+        let parsed = json::parse(json_str)?;
+        let contracts = &parsed["contracts"];
+
+        if !contracts.is_object() {
+            return Err("Invalid API Set schema file, missing \"contracts\" object".into());
+        }
+
+        let mut apiset = APISet::new();
+
+        apiset.version = parsed["version"].as_u32().unwrap_or(0);
+
+        for (api_set_name, contract_value) in contracts.entries() {
+            let invalid_contract = || format!("Invalid contract for API Set \"{api_set_name}\"");
+
+            let mut contract = APISetContract::new();
+
+            if let Some(host_dll_name) = contract_value.as_str() {
+                let mut host = APISetHost::new();
+                host.host = host_dll_name.to_ascii_lowercase();
+
+                contract.hosts.push(host);
+            } else if contract_value.is_object() {
+                contract.flags = contract_value["flags"].as_u32().unwrap_or(0);
+
+                for host_value in contract_value["hosts"].members() {
+                    let mut host = APISetHost::new();
+
+                    match host_value.as_str() {
+                        Some(host_dll_name) => host.host = host_dll_name.to_ascii_lowercase(),
+                        None => {
+                            host.importer = host_value["importer"]
+                                .as_str()
+                                .unwrap_or("")
+                                .to_ascii_lowercase();
+                            host.host = host_value["host"]
+                                .as_str()
+                                .ok_or_else(invalid_contract)?
+                                .to_ascii_lowercase();
+                        }
+                    }
+
+                    contract.hosts.push(host);
+                }
+            } else {
+                return Err(invalid_contract().into());
+            }
+
+            let api_set_name = api_set_name.to_ascii_lowercase();
+
+            apiset.contracts.insert(
+                api_set_name
+                    .strip_suffix(".dll")
+                    .unwrap_or(&api_set_name)
+                    .to_string(),
+                contract,
+            );
+        }
+
+        if apiset.version >= 6 {
+            apiset.build_hash_table();
+        }
+
+        return Ok(apiset);
     }
 }
 
-fn parse_apiset(apiset_dll: super::pe::PE) -> Result<APISet, Box<dyn std::error::Error>> {
-    log::trace!("Parsing apisetschema mapping");
+/* Hash of the v6 schemas, computed on the utf-16 lowercase name */
+fn hash_api_set_name(name: &str, multiplier: u32) -> u32 {
+    return name.encode_utf16().fold(0u32, |hash, c| {
+        hash.wrapping_mul(multiplier).wrapping_add(c as u32)
+    });
+}
+
+fn read_utf16_string(
+    cursor: &mut std::io::Cursor<&[u8]>,
+    offset: u64,
+    length: u32,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut buffer: Vec<u8> = vec![0; length as usize];
+
+    cursor.set_position(offset);
+    cursor.read_exact(&mut buffer)?;
 
+    let utf16: Vec<u16> = buffer
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .collect();
+
+    return Ok(String::from_utf16(&utf16)
+        .map_err(|_| "Invalid utf-16 name in API Set schema")?
+        .trim_end_matches('\0')
+        .to_ascii_lowercase());
+}
+
+fn read_host(
+    cursor: &mut std::io::Cursor<&[u8]>,
+    namespace_start: u64,
+    name_offset: u32,
+    name_length: u32,
+    value_offset: u32,
+    value_length: u32,
+) -> Result<APISetHost, Box<dyn std::error::Error>> {
+    let mut host = APISetHost::new();
+
+    host.importer = read_utf16_string(cursor, namespace_start + name_offset as u64, name_length)?;
+    host.host = read_utf16_string(cursor, namespace_start + value_offset as u64, value_length)?;
+
+    return Ok(host);
+}
+
+/*
+ * Windows 7 layout. Names are stored without the "api-" prefix, and all offsets are relative to
+ * the start of the namespace
+ */
+fn parse_apiset_v2(
+    cursor: &mut std::io::Cursor<&[u8]>,
+    namespace_start: u64,
+) -> Result<APISet, Box<dyn std::error::Error>> {
     let mut apiset: APISet = APISet::new();
 
-    let apiset_section = apiset_dll
-        .sections
-        .get(".apiset")
-        .expect("Cannot find .apiset section in apiset dll");
+    let asn = APISetNamespaceV2::from_parser(cursor)?;
 
-    let mut cursor = std::io::Cursor::new(&apiset_dll.data);
+    apiset.version = asn.version;
 
-    let section_start = apiset_section.header.ptr_to_raw_data as u64;
+    for i in 0..asn.count as u64 {
+        cursor.set_position(
+            namespace_start + 8 + i * std::mem::size_of::<APISetNamespaceEntryV2>() as u64,
+        );
 
-    cursor.set_position(section_start);
+        let asne = APISetNamespaceEntryV2::from_parser(cursor)?;
 
-    let asn = APISetNamespace::from_parser(&mut cursor)?;
+        let api_set_name = read_utf16_string(
+            cursor,
+            namespace_start + asne.name_offset as u64,
+            asne.name_length,
+        )?;
 
-    cursor.set_position(section_start + asn.entry_offset as u64);
+        let values_start = namespace_start + asne.data_offset as u64;
 
-    for _ in 0..asn.count {
-        let cursor_position = cursor.position();
-        let asne = APISetNamespaceEntry::from_parser(&mut cursor)?;
-        let mut name_buffer: Vec<u8> = vec![0; asne.name_length as usize];
+        cursor.set_position(values_start);
 
-        cursor.set_position(section_start + asne.name_offset as u64);
-        cursor.read_exact(name_buffer.as_mut())?;
+        let value_count = cursor.read_u32::<LittleEndian>()?;
 
-        let (front, slice, back) = unsafe { name_buffer.as_slice().align_to::<u16>() };
+        let mut contract = APISetContract::new();
+        contract.hashed_length = api_set_name.len() + 4;
 
-        if !front.is_empty() && !back.is_empty() {
-            return Err("Error while trying to read name of APISetNamespaceEntry".into());
+        for j in 0..value_count as u64 {
+            cursor.set_position(
+                values_start + 4 + j * std::mem::size_of::<APISetValueEntryV2>() as u64,
+            );
+
+            let asve = APISetValueEntryV2::from_parser(cursor)?;
+
+            contract.hosts.push(read_host(
+                cursor,
+                namespace_start,
+                asve.name_offset,
+                asve.name_length,
+                asve.value_offset,
+                asve.value_length,
+            )?);
         }
 
-        let api_set_name = String::from_utf16(slice).expect("Invalid utf-16 name");
+        apiset
+            .contracts
+            .insert(format!("api-{api_set_name}"), contract);
+    }
 
-        if asne.value_count > 0 {
-            cursor.set_position(section_start + asne.value_offset as u64);
+    return Ok(apiset);
+}
+
+/*
+ * Windows 8 and 8.1 layout. Names are stored without the "api-" or "ext-" prefix, which is given
+ * by the extension flag of the entry
+ */
+fn parse_apiset_v4(
+    cursor: &mut std::io::Cursor<&[u8]>,
+    namespace_start: u64,
+) -> Result<APISet, Box<dyn std::error::Error>> {
+    let mut apiset: APISet = APISet::new();
 
-            let asve = APISetValueEntry::from_parser(&mut cursor)?;
+    let asn = APISetNamespaceV4::from_parser(cursor)?;
 
-            let mut value_buffer: Vec<u8> = vec![0; asve.value_length as usize];
-            cursor.set_position(section_start + asve.value_offset as u64);
-            cursor.read_exact(&mut value_buffer)?;
+    apiset.version = asn.version;
 
-            let (front, slice, back) = unsafe { value_buffer.as_slice().align_to::<u16>() };
+    for i in 0..asn.count as u64 {
+        cursor.set_position(
+            namespace_start + 16 + i * std::mem::size_of::<APISetNamespaceEntryV4>() as u64,
+        );
 
-            if !front.is_empty() && !back.is_empty() {
-                return Err("Error while trying to read name of APISetNamespaceEntry".into());
-            }
+        let asne = APISetNamespaceEntryV4::from_parser(cursor)?;
+
+        let api_set_name = read_utf16_string(
+            cursor,
+            namespace_start + asne.name_offset as u64,
+            asne.name_length,
+        )?;
 
-            let host_dll_name = String::from_utf16(slice).expect("Invalid utf-16 name");
+        let prefix = if asne.flags & API_SET_SCHEMA_ENTRY_FLAGS_EXTENSION != 0 {
+            "ext-"
+        } else {
+            "api-"
+        };
 
-            apiset.mapping.insert(
-                api_set_name.trim_end_matches('\0').to_ascii_lowercase(),
-                host_dll_name.trim_end_matches('\0').to_ascii_lowercase(),
+        let values_start = namespace_start + asne.data_offset as u64;
+
+        cursor.set_position(values_start);
+
+        let _value_array_flags = cursor.read_u32::<LittleEndian>()?;
+        let value_count = cursor.read_u32::<LittleEndian>()?;
+
+        let mut contract = APISetContract::new();
+        contract.flags = asne.flags;
+        contract.hashed_length = api_set_name.len() + 4;
+
+        for j in 0..value_count as u64 {
+            cursor.set_position(
+                values_start + 8 + j * std::mem::size_of::<APISetValueEntry>() as u64,
             );
+
+            let asve = APISetValueEntry::from_parser(cursor)?;
+
+            contract.hosts.push(read_host(
+                cursor,
+                namespace_start,
+                asve.name_offset,
+                asve.name_length,
+                asve.value_offset,
+                asve.value_length,
+            )?);
         }
 
-        cursor.set_position(cursor_position + std::mem::size_of::<APISetNamespaceEntry>() as u64);
+        apiset
+            .contracts
+            .insert(format!("{prefix}{api_set_name}"), contract);
     }
 
-    log::trace!("Parsed apisetschema mapping");
+    return Ok(apiset);
+}
+
+/* Windows 10 and later layout. Names are stored with their prefix */
+fn parse_apiset_v6(
+    cursor: &mut std::io::Cursor<&[u8]>,
+    namespace_start: u64,
+) -> Result<APISet, Box<dyn std::error::Error>> {
+    let mut apiset: APISet = APISet::new();
+
+    let asn = APISetNamespace::from_parser(cursor)?;
+
+    apiset.version = asn.version;
+    apiset.hash_multiplier = asn.hash_multiplier;
+
+    let mut names: Vec<String> = Vec::new();
+
+    for i in 0..asn.count as u64 {
+        cursor.set_position(
+            namespace_start
+                + asn.entry_offset as u64
+                + i * std::mem::size_of::<APISetNamespaceEntry>() as u64,
+        );
+
+        let asne = APISetNamespaceEntry::from_parser(cursor)?;
+
+        let api_set_name = read_utf16_string(
+            cursor,
+            namespace_start + asne.name_offset as u64,
+            asne.name_length,
+        )?;
+
+        let mut contract = APISetContract::new();
+        contract.flags = asne.flags;
+        contract.hashed_length = asne.hashed_length as usize / 2;
+
+        for j in 0..asne.value_count as u64 {
+            cursor.set_position(
+                namespace_start
+                    + asne.value_offset as u64
+                    + j * std::mem::size_of::<APISetValueEntry>() as u64,
+            );
+
+            let asve = APISetValueEntry::from_parser(cursor)?;
+
+            contract.hosts.push(read_host(
+                cursor,
+                namespace_start,
+                asve.name_offset,
+                asve.name_length,
+                asve.value_offset,
+                asve.value_length,
+            )?);
+        }
+
+        names.push(api_set_name.clone());
+        apiset.contracts.insert(api_set_name, contract);
+    }
+
+    for i in 0..asn.count as u64 {
+        cursor.set_position(
+            namespace_start
+                + asn.hash_offset as u64
+                + i * std::mem::size_of::<APISetHashEntry>() as u64,
+        );
+
+        let ashe = APISetHashEntry::from_parser(cursor)?;
+
+        let name = names.get(ashe.index as usize).ok_or(format!(
+            "Invalid API Set hash entry, index {} out of range",
+            ashe.index
+        ))?;
+
+        apiset.hash_table.push((ashe.hash, name.clone()));
+    }
+
+    return Ok(apiset);
+}
+
+fn parse_apiset(apiset_dll: super::pe::PE) -> Result<APISet, Box<dyn std::error::Error>> {
+    log::trace!("Parsing apisetschema mapping");
+
+    let apiset_section = apiset_dll
+        .sections
+        .get(".apiset")
+        .ok_or("Cannot find .apiset section in apiset dll")?;
+
+    let mut cursor = std::io::Cursor::new(&apiset_dll.data[..]);
+
+    let section_start = apiset_section.header.ptr_to_raw_data as u64;
+
+    cursor.set_position(section_start);
+
+    /* All the versions of the namespace header start with the version number */
+    let version = cursor.read_u32::<LittleEndian>()?;
+
+    cursor.set_position(section_start);
+
+    let mut apiset = match version {
+        2 => parse_apiset_v2(&mut cursor, section_start)?,
+        4 => parse_apiset_v4(&mut cursor, section_start)?,
+        6 => parse_apiset_v6(&mut cursor, section_start)?,
+        _ => return Err(format!("Unsupported API Set schema version {version}").into()),
+    };
+
+    apiset.complete = true;
+
+    log::trace!(
+        "Parsed apisetschema mapping (version {}, {} entries)",
+        apiset.version,
+        apiset.contracts.len()
+    );
 
     return Ok(apiset);
 }
 
-pub fn load_apisetschema_mapping() -> Result<APISet, Box<dyn std::error::Error>> {
-    log::trace!("Loading apisetschema mapping");
+/* Loads the mapping from either an apisetschema.dll or a json serialized mapping */
+pub fn load_apisetschema_mapping(schema_path: &Path) -> Result<APISet, Box<dyn std::error::Error>> {
+    log::trace!(
+        "Loading apisetschema mapping from {}",
+        schema_path.display()
+    );
 
-    let p = PathBuf::from_str(API_SET_SCHEMA_DLL_PATH)?;
-    let pe = super::pe::parse_pe(&p)?;
+    let data = std::fs::read(schema_path).map_err(|err| {
+        format!(
+            "Cannot read API Set schema \"{}\": {err}",
+            schema_path.display()
+        )
+    })?;
+
+    if !data.starts_with(b"MZ") {
+        return APISet::from_json(std::str::from_utf8(&data)?);
+    }
+
+    let pe = super::pe::parse_pe_data(super::pe::PEData::Owned(data))?;
 
     log::trace!("Parsed apisetschema dll");
 
     return parse_apiset(pe);
 }
 
+pub fn load_embedded_apisetschema_mapping() -> Result<APISet, Box<dyn std::error::Error>> {
+    log::trace!("Loading embedded apisetschema mapping");
+
+    return APISet::from_json(EMBEDDED_API_SET_SCHEMA);
+}
+
+/*
+ * Contracts added, removed or changed (flags or hosts) between two schemas
+ */
+pub fn diff_apisets(old: &APISet, new: &APISet) -> json::JsonValue {
+    let mut added = json::JsonValue::new_object();
+    let mut removed = json::JsonValue::new_object();
+    let mut changed = json::JsonValue::new_object();
+
+    for (name, old_contract) in old.get_contracts() {
+        match new.contracts.get(name) {
+            Some(new_contract) if new_contract != old_contract => {
+                changed[name.as_str()] = json::object! {
+                    old: old_contract.to_json(),
+                    new: new_contract.to_json(),
+                };
+            }
+            Some(_) => {}
+            None => removed[name.as_str()] = old_contract.to_json(),
+        }
+    }
+
+    for (name, new_contract) in new.get_contracts() {
+        if !old.contracts.contains_key(name) {
+            added[name.as_str()] = new_contract.to_json();
+        }
+    }
+
+    return json::object! {
+        old_version: old.version,
+        new_version: new.version,
+        added: added,
+        removed: removed,
+        changed: changed,
+    };
+}
+
+/* One line per difference, prefixed with "+" (added), "-" (removed) or "~" (changed) */
+pub fn diff_apisets_to_table(old: &APISet, new: &APISet) -> String {
+    let mut table = String::new();
+
+    for (name, old_contract) in old.get_contracts() {
+        match new.contracts.get(name) {
+            Some(new_contract) if new_contract != old_contract => {
+                if old_contract.hosts != new_contract.hosts {
+                    table.push_str(&format!(
+                        "~ {name}: {} -> {}\n",
+                        old_contract.hosts_to_string(),
+                        new_contract.hosts_to_string()
+                    ));
+                }
+
+                if old_contract.flags != new_contract.flags {
+                    table.push_str(&format!(
+                        "~ {name}: flags {:#x} -> {:#x}\n",
+                        old_contract.flags, new_contract.flags
+                    ));
+                }
+            }
+            Some(_) => {}
+            None => {
+                table.push_str(&format!("- {name}: {}\n", old_contract.hosts_to_string()));
+            }
+        }
+    }
+
+    for (name, new_contract) in new.get_contracts() {
+        if !old.contracts.contains_key(name) {
+            table.push_str(&format!("+ {name}: {}\n", new_contract.hosts_to_string()));
+        }
+    }
+
+    return table;
+}
+
+/* Like the loader, any name starting with "api-" or "ext-" (ignoring the case) is an API Set */
 pub fn is_dll_from_apiset_schema(name: &str) -> bool {
-    return name.starts_with("api-ms-win") || name.starts_with("ext-ms-win");
+    return match name.get(..4) {
+        Some(prefix) => prefix.eq_ignore_ascii_case("api-") || prefix.eq_ignore_ascii_case("ext-"),
+        None => false,
+    };
 }
 
-pub fn find_dll(name: &str, apiset_schema: &APISet) -> Option<String> {
-    let res = apiset_schema.map(name);
+/*
+ * Extension API Sets are optional, and may have no host on a given system. Programs check for them
+ * at runtime, so a missing one is not a failure. Only a complete schema tells that an extension is
+ * absent, a partial one may just not list it
+ */
+pub fn is_absent_extension(name: &str, apiset_schema: &APISet) -> bool {
+    return is_dll_from_apiset_schema(name)
+        && name[..4].eq_ignore_ascii_case("ext-")
+        && apiset_schema.is_complete()
+        && apiset_schema.map(name, None).is_none();
+}
+
+pub fn find_dll(name: &str, apiset_schema: &APISet, importer: Option<&str>) -> Option<String> {
+    let res = apiset_schema.map(name, importer);
 
     if res.is_none() {
         log::trace!("Could not resolve API Set DLL: {name}");
@@ -216,3 +1038,447 @@ pub fn find_dll(name: &str, apiset_schema: &APISet) -> Option<String> {
 
     return res;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::pe::test_image::ImageBuilder;
+    use super::super::pe::{self, IMAGE_FILE_MACHINE_AMD64};
+    use super::*;
+
+    /* (name without its prefix, flags, [(importer, host)]) */
+    type Contracts<'a> = &'a [(&'a str, u32, &'a [(&'a str, &'a str)])];
+
+    /*
+     * Namespace of a v2 or v4 schema: the header, the entries, the value arrays and the utf-16
+     * strings, in that order. The flags of the contracts are only stored by v4
+     */
+    fn build_namespace(version: u32, contracts: Contracts) -> Vec<u8> {
+        let (header_size, entry_size, values_header_size, value_size) = match version {
+            2 => (8, 12, 4, 16),
+            _ => (16, 24, 8, 20),
+        };
+
+        let host_count: usize = contracts.iter().map(|(_, _, hosts)| hosts.len()).sum();
+        let values_offset = header_size + entry_size * contracts.len();
+        let strings_offset =
+            values_offset + values_header_size * contracts.len() + value_size * host_count;
+
+        let mut strings: Vec<u8> = Vec::new();
+
+        let mut add_string = |string: &str| -> [u32; 2] {
+            let offset = (strings_offset + strings.len()) as u32;
+            let length = string.len() as u32 * 2;
+
+            strings.extend(string.encode_utf16().flat_map(u16::to_le_bytes));
+
+            return [offset, length];
+        };
+
+        let mut entries: Vec<u32> = Vec::new();
+        let mut values: Vec<u32> = Vec::new();
+
+        for (name, flags, hosts) in contracts {
+            let [name_offset, name_length] = add_string(name);
+            let data_offset = (values_offset + values.len() * 4) as u32;
+
+            if version == 2 {
+                entries.extend([name_offset, name_length, data_offset]);
+                values.push(hosts.len() as u32);
+            } else {
+                entries.extend([
+                    *flags,
+                    name_offset,
+                    name_length,
+                    name_offset,
+                    name_length,
+                    data_offset,
+                ]);
+                values.extend([0, hosts.len() as u32]);
+            }
+
+            for (importer, host) in hosts.iter() {
+                if version != 2 {
+                    values.push(0);
+                }
+
+                values.extend(add_string(importer));
+                values.extend(add_string(host));
+            }
+        }
+
+        let header: Vec<u32> = match version {
+            2 => vec![2, contracts.len() as u32],
+            _ => vec![
+                version,
+                (strings_offset + strings.len()) as u32,
+                0,
+                contracts.len() as u32,
+            ],
+        };
+
+        let mut namespace: Vec<u8> = [header, entries, values]
+            .concat()
+            .iter()
+            .flat_map(|dword| dword.to_le_bytes())
+            .collect();
+
+        assert_eq!(namespace.len(), strings_offset);
+
+        namespace.extend(strings);
+
+        return namespace;
+    }
+
+    /* apisetschema.dll holding the namespace at the start of its .apiset section */
+    fn parse_namespace(namespace: &[u8]) -> Result<APISet, Box<dyn std::error::Error>> {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.set_section_name(".apiset");
+        image.append(namespace);
+
+        return parse_apiset(pe::parse_pe_data(pe::PEData::Owned(image.build()))?);
+    }
+
+    fn get_hosts(apiset: &APISet, name: &str) -> Vec<(String, String)> {
+        return apiset.contracts[name]
+            .hosts
+            .iter()
+            .map(|host| (host.importer.clone(), host.host.clone()))
+            .collect();
+    }
+
+    #[test]
+    fn api_set_names_are_hashed_on_their_utf16_characters() {
+        assert_eq!(hash_api_set_name("", DEFAULT_HASH_MULTIPLIER), 0);
+        assert_eq!(hash_api_set_name("a", DEFAULT_HASH_MULTIPLIER), 0x61);
+        assert_eq!(
+            hash_api_set_name("ab", DEFAULT_HASH_MULTIPLIER),
+            0x61 * 0x1F + 0x62
+        );
+
+        /* The hash wraps around on long names */
+        let name = "api-ms-win-core-file-l1-2";
+        let expected = name.bytes().fold(0u64, |hash, c| {
+            (hash * DEFAULT_HASH_MULTIPLIER as u64 + c as u64) % (1u64 << 32)
+        });
+
+        assert_eq!(
+            hash_api_set_name(name, DEFAULT_HASH_MULTIPLIER) as u64,
+            expected
+        );
+    }
+
+    #[test]
+    fn contracts_match_any_revision() {
+        let apiset = APISet::from_json(
+            r#"{ "version": 6, "contracts": { "api-ms-win-core-file-l1-2-0": "kernel32.dll" } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            apiset
+                .map("api-ms-win-core-file-l1-2-4.dll", None)
+                .as_deref(),
+            Some("kernel32.dll")
+        );
+        assert_eq!(
+            apiset
+                .map("API-MS-WIN-CORE-FILE-L1-2-0.DLL", None)
+                .as_deref(),
+            Some("kernel32.dll")
+        );
+        assert!(
+            apiset
+                .get_contract("api-ms-win-core-file-l1-1-0.dll")
+                .is_none()
+        );
+        assert!(
+            apiset
+                .get_contract("api-ms-win-core-file-l1-2.dll")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn contracts_resolve_to_their_highest_revision() {
+        let apiset = APISet::from_json(
+            r#"{
+                "version": 6,
+                "contracts": {
+                    "api-ms-win-core-file-l1-2-0": "kernel32.dll",
+                    "api-ms-win-core-file-l1-2-10": "kernelbase.dll",
+                    "api-ms-win-core-file-l1-2-1": "kernel32.dll",
+                    "api-ms-win-core-file-l1-2-2": "kernel32.dll",
+                    "api-ms-win-core-file-l1-2-3": "kernel32.dll"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        for name in [
+            "api-ms-win-core-file-l1-2-0",
+            "api-ms-win-core-file-l1-2-3",
+            "api-ms-win-core-file-l1-2-12",
+        ] {
+            assert_eq!(apiset.map(name, None).as_deref(), Some("kernelbase.dll"));
+        }
+    }
+
+    #[test]
+    fn embedded_schema_lookups_use_the_highest_revision() {
+        let apiset = load_embedded_apisetschema_mapping().unwrap();
+
+        for (name, _) in apiset.get_contracts() {
+            let hashed_name = &name[..name.rfind('-').unwrap()];
+            let latest = format!("{hashed_name}-99");
+
+            let highest_revision = apiset
+                .get_contracts()
+                .into_iter()
+                .filter(|(other, _)| other.rfind('-').map(|end| &other[..end]) == Some(hashed_name))
+                .max_by_key(|(other, _)| other[hashed_name.len() + 1..].parse::<u32>().unwrap())
+                .map(|(_, contract)| contract);
+
+            assert_eq!(apiset.get_contract(&latest), highest_revision, "{name}");
+        }
+    }
+
+    #[test]
+    fn only_complete_schemas_report_absent_extensions() {
+        let mut apiset = load_embedded_apisetschema_mapping().unwrap();
+
+        assert!(!is_absent_extension(
+            "ext-ms-win-fdw-test-l1-1-0.dll",
+            &apiset
+        ));
+
+        apiset.complete = true;
+
+        assert!(is_absent_extension(
+            "ext-ms-win-fdw-test-l1-1-0.dll",
+            &apiset
+        ));
+        assert!(!is_absent_extension(
+            "api-ms-win-fdw-test-l1-1-0.dll",
+            &apiset
+        ));
+        assert!(!is_absent_extension(
+            "ext-ms-win-fdw-test-l1-1-0.dll",
+            &APISet::new()
+        ));
+    }
+
+    #[test]
+    fn v2_schemas_are_parsed_with_the_api_prefix() {
+        let namespace = build_namespace(
+            2,
+            &[
+                ("ms-win-core-file-l1-1-0", 0, &[("", "kernel32.dll")]),
+                (
+                    "ms-win-core-com-l1-1-0",
+                    0,
+                    &[("", "ole32.dll"), ("ole32.dll", "combase.dll")],
+                ),
+            ],
+        );
+
+        let apiset = parse_namespace(&namespace).unwrap();
+
+        assert_eq!(apiset.get_version(), 2);
+        assert!(apiset.is_complete());
+        assert_eq!(
+            apiset
+                .get_contracts()
+                .into_iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<&str>>(),
+            ["api-ms-win-core-com-l1-1-0", "api-ms-win-core-file-l1-1-0"]
+        );
+        assert_eq!(
+            get_hosts(&apiset, "api-ms-win-core-com-l1-1-0"),
+            [
+                ("".to_string(), "ole32.dll".to_string()),
+                ("ole32.dll".to_string(), "combase.dll".to_string())
+            ]
+        );
+        assert_eq!(
+            apiset
+                .map("api-ms-win-core-file-l1-1-0.dll", None)
+                .as_deref(),
+            Some("kernel32.dll")
+        );
+        assert_eq!(
+            apiset
+                .map("api-ms-win-core-com-l1-1-0.dll", Some("ole32.dll"))
+                .as_deref(),
+            Some("combase.dll")
+        );
+    }
+
+    #[test]
+    fn v4_schemas_take_the_prefix_from_the_extension_flag() {
+        let namespace = build_namespace(
+            4,
+            &[
+                (
+                    "ms-win-core-file-l1-2-0",
+                    API_SET_SCHEMA_ENTRY_FLAGS_SEALED,
+                    &[("", "kernel32.dll"), ("kernel32.dll", "kernelbase.dll")],
+                ),
+                (
+                    "ms-win-ntuser-window-l1-1-0",
+                    API_SET_SCHEMA_ENTRY_FLAGS_EXTENSION,
+                    &[("", "user32.dll")],
+                ),
+            ],
+        );
+
+        let apiset = parse_namespace(&namespace).unwrap();
+
+        assert_eq!(apiset.get_version(), 4);
+
+        let contract = apiset
+            .get_contract("api-ms-win-core-file-l1-2-0.dll")
+            .unwrap();
+
+        assert!(contract.is_sealed());
+        assert!(!contract.is_extension());
+        assert_eq!(
+            get_hosts(&apiset, "api-ms-win-core-file-l1-2-0"),
+            [
+                ("".to_string(), "kernel32.dll".to_string()),
+                ("kernel32.dll".to_string(), "kernelbase.dll".to_string())
+            ]
+        );
+
+        let contract = apiset
+            .get_contract("ext-ms-win-ntuser-window-l1-1-0.dll")
+            .unwrap();
+
+        assert!(contract.is_extension());
+        assert_eq!(contract.get_host(None), Some("user32.dll"));
+        assert!(
+            apiset
+                .get_contract("api-ms-win-ntuser-window-l1-1-0.dll")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn unknown_schema_versions_are_rejected() {
+        let namespace = build_namespace(5, &[("ms-win-core-file-l1-1-0", 0, &[])]);
+
+        let err = parse_namespace(&namespace).unwrap_err();
+
+        assert_eq!(err.to_string(), "Unsupported API Set schema version 5");
+    }
+
+    fn host(importer: &str, host: &str) -> APISetHost {
+        return APISetHost {
+            importer: importer.to_string(),
+            host: host.to_string(),
+        };
+    }
+
+    #[test]
+    fn exceptions_are_matched_on_the_importer() {
+        let mut contract = APISetContract::new();
+
+        /* The importer name left on the default host does not make it an exception */
+        contract.hosts = vec![
+            host("kernel32.dll", "kernel32.dll"),
+            host("KERNEL32.DLL", "kernelbase.dll"),
+        ];
+
+        assert_eq!(
+            contract.get_host(Some("kernel32.dll")),
+            Some("kernelbase.dll")
+        );
+        assert_eq!(
+            contract.get_host(Some("Kernel32.DLL")),
+            Some("kernelbase.dll")
+        );
+        assert_eq!(contract.get_host(Some("user32.dll")), Some("kernel32.dll"));
+        assert_eq!(contract.get_host(Some("kernel32")), Some("kernel32.dll"));
+        assert_eq!(contract.get_host(None), Some("kernel32.dll"));
+
+        /* An empty host leaves the contract unresolved */
+        contract.hosts.push(host("ole32.dll", ""));
+
+        assert_eq!(contract.get_host(Some("ole32.dll")), None);
+        assert_eq!(contract.get_host(Some("combase.dll")), Some("kernel32.dll"));
+    }
+
+    #[test]
+    fn schemas_are_diffed_by_contract() {
+        let old = APISet::from_json(
+            r#"{
+                "version": 4,
+                "contracts": {
+                    "api-ms-win-core-file-l1-1-0": "kernel32.dll",
+                    "api-ms-win-core-com-l1-1-0": "ole32.dll",
+                    "api-ms-win-core-heap-l1-1-0": "kernel32.dll",
+                    "ext-ms-win-ntuser-window-l1-1-0": { "flags": 2, "hosts": ["user32.dll"] }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let new = APISet::from_json(
+            r#"{
+                "version": 6,
+                "contracts": {
+                    "api-ms-win-core-file-l1-1-0": "kernel32.dll",
+                    "api-ms-win-core-com-l1-1-0": {
+                        "hosts": ["combase.dll", { "importer": "ole32.dll", "host": "ole32.dll" }]
+                    },
+                    "ext-ms-win-ntuser-window-l1-1-0": { "flags": 3, "hosts": ["user32.dll"] },
+                    "api-ms-win-core-synch-l1-2-0": "kernelbase.dll"
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let diff = diff_apisets(&old, &new);
+
+        assert_eq!(diff["old_version"], 4);
+        assert_eq!(diff["new_version"], 6);
+        assert_eq!(
+            diff["added"]["api-ms-win-core-synch-l1-2-0"]["hosts"][0],
+            "kernelbase.dll"
+        );
+        assert_eq!(diff["added"].len(), 1);
+        assert_eq!(
+            diff["removed"]["api-ms-win-core-heap-l1-1-0"]["hosts"][0],
+            "kernel32.dll"
+        );
+        assert_eq!(diff["removed"].len(), 1);
+        assert_eq!(
+            diff["changed"]["api-ms-win-core-com-l1-1-0"]["old"]["hosts"][0],
+            "ole32.dll"
+        );
+        assert_eq!(
+            diff["changed"]["api-ms-win-core-com-l1-1-0"]["new"]["hosts"][1]["importer"],
+            "ole32.dll"
+        );
+        assert_eq!(
+            diff["changed"]["ext-ms-win-ntuser-window-l1-1-0"]["new"]["sealed"],
+            true
+        );
+        assert_eq!(diff["changed"].len(), 2);
+
+        assert_eq!(
+            diff_apisets_to_table(&old, &new),
+            "~ api-ms-win-core-com-l1-1-0: ole32.dll -> combase.dll, ole32.dll (for ole32.dll)\n\
+             - api-ms-win-core-heap-l1-1-0: kernel32.dll\n\
+             ~ ext-ms-win-ntuser-window-l1-1-0: flags 0x2 -> 0x3\n\
+             + api-ms-win-core-synch-l1-2-0: kernelbase.dll\n"
+        );
+
+        let diff = diff_apisets(&old, &old);
+
+        assert!(
+            diff["added"].is_empty() && diff["removed"].is_empty() && diff["changed"].is_empty()
+        );
+        assert_eq!(diff_apisets_to_table(&old, &old), "");
+    }
+}
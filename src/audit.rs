@@ -0,0 +1,424 @@
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use super::pe::{self, Characteristics, DllCharacteristics, Machine, PE};
+
+/*
+ * Not expected from every image: CET needs a compatible toolchain and dependencies, and forced
+ * integrity checks need a signed image
+ */
+const OPTIONAL_MITIGATIONS: &[&str] = &["cet_compatible", "force_integrity"];
+
+/*
+ * Security mitigations an image opts in to. Mitigations that do not apply to the machine of the
+ * image (SafeSEH outside of x86, high entropy ASLR for 32-bit images) are None
+ */
+#[derive(Default, Clone, Debug)]
+pub struct Mitigations {
+    machine: Machine,
+    dynamic_base: bool,
+    high_entropy_va: Option<bool>,
+    nx_compat: bool,
+    cfg: bool,
+    safe_seh: Option<bool>,
+    gs: bool,
+    cet_compatible: bool,
+    force_integrity: bool,
+}
+
+impl Mitigations {
+    pub fn new() -> Mitigations {
+        return Mitigations::default();
+    }
+
+    pub fn from_pe(pe: &PE) -> Mitigations {
+        let mut mitigations = Mitigations::new();
+
+        let dll_characteristics = pe.get_dll_characteristics();
+        let load_config = pe.get_load_config();
+
+        mitigations.machine = pe.get_machine();
+
+        /* An image without relocations is always loaded at its preferred base */
+        mitigations.dynamic_base = dll_characteristics.contains(DllCharacteristics::DYNAMIC_BASE)
+            && !pe
+                .get_characteristics()
+                .contains(Characteristics::RELOCS_STRIPPED);
+
+        if !pe.is_32_bits() {
+            mitigations.high_entropy_va = Some(
+                mitigations.dynamic_base
+                    && dll_characteristics.contains(DllCharacteristics::HIGH_ENTROPY_VA),
+            );
+        }
+
+        mitigations.nx_compat = dll_characteristics.contains(DllCharacteristics::NX_COMPAT);
+
+        /* The flag alone is not enough, the linker also has to emit the CF function table */
+        mitigations.cfg = dll_characteristics.contains(DllCharacteristics::GUARD_CF)
+            && load_config.is_some_and(|load_config| {
+                load_config.get_guard_flags() & pe::IMAGE_GUARD_CF_INSTRUMENTED != 0
+            });
+
+        /* Images without any exception handler are safe as well */
+        if mitigations.machine == Machine::I386 {
+            mitigations.safe_seh = Some(
+                dll_characteristics.contains(DllCharacteristics::NO_SEH)
+                    || load_config
+                        .is_some_and(|load_config| load_config.get_se_handler_table() != 0),
+            );
+        }
+
+        mitigations.gs =
+            load_config.is_some_and(|load_config| load_config.get_security_cookie() != 0);
+
+        mitigations.cet_compatible =
+            pe.get_ex_dll_characteristics() & pe::IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT != 0;
+
+        mitigations.force_integrity =
+            dll_characteristics.contains(DllCharacteristics::FORCE_INTEGRITY);
+
+        return mitigations;
+    }
+
+    fn get_checks(&self) -> Vec<(&'static str, Option<bool>)> {
+        return vec![
+            ("dynamic_base", Some(self.dynamic_base)),
+            ("high_entropy_va", self.high_entropy_va),
+            ("nx_compat", Some(self.nx_compat)),
+            ("cfg", Some(self.cfg)),
+            ("safe_seh", self.safe_seh),
+            ("gs", Some(self.gs)),
+            ("cet_compatible", Some(self.cet_compatible)),
+            ("force_integrity", Some(self.force_integrity)),
+        ];
+    }
+
+    fn get_disabled(&self, optional: bool) -> Vec<&'static str> {
+        return self
+            .get_checks()
+            .into_iter()
+            .filter(|(name, enabled)| {
+                *enabled == Some(false) && OPTIONAL_MITIGATIONS.contains(name) == optional
+            })
+            .map(|(name, _)| name)
+            .collect();
+    }
+
+    /*
+     * Applicable mitigations the image does not opt in to
+     */
+    pub fn get_missing(&self) -> Vec<&'static str> {
+        return self.get_disabled(false);
+    }
+
+    /*
+     * Hardening the image does not opt in to, that most images go without
+     */
+    pub fn get_missing_optional(&self) -> Vec<&'static str> {
+        return self.get_disabled(true);
+    }
+
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut result = json::object! {
+            machine: self.machine.to_string(),
+        };
+
+        for (name, enabled) in self.get_checks() {
+            result[name] = enabled.into();
+        }
+
+        result["missing"] = self.get_missing().into();
+        result["missing_optional"] = self.get_missing_optional().into();
+
+        return result;
+    }
+}
+
+fn audit_file(path: &Path, pe: &Result<Rc<PE>, String>) -> json::JsonValue {
+    let mut result = json::object! {
+        path: path.to_str().unwrap_or("<invalid path>"),
+    };
+
+    match pe {
+        Ok(pe) => {
+            for (key, value) in Mitigations::from_pe(pe).to_json().entries() {
+                result[key] = value.clone();
+            }
+        }
+        Err(err) => {
+            result["error"] = format!("Failed to parse PE \"{}\" ({})", path.display(), err).into();
+        }
+    }
+
+    return result;
+}
+
+/*
+ * Audits the files of a dependency walk, the root file and every dll of its dependency tree, from
+ * the images the walk already parsed
+ */
+pub fn audit_dependencies(files: &[(PathBuf, Result<Rc<PE>, String>)]) -> json::JsonValue {
+    return json::JsonValue::Array(
+        files
+            .iter()
+            .map(|(path, pe)| audit_file(path, pe))
+            .collect(),
+    );
+}
+
+pub fn audit_to_table(audit: &json::JsonValue) -> String {
+    const COLUMNS: &[(&str, &str)] = &[
+        ("dynamic_base", "ASLR"),
+        ("high_entropy_va", "HEVA"),
+        ("nx_compat", "NX"),
+        ("cfg", "CFG"),
+        ("safe_seh", "SAFESEH"),
+        ("gs", "GS"),
+        ("cet_compatible", "CET"),
+        ("force_integrity", "INTEGRITY"),
+    ];
+
+    let path_width = audit
+        .members()
+        .map(|file| file["path"].as_str().unwrap_or("").len())
+        .max()
+        .unwrap_or(0)
+        .max("PATH".len());
+
+    let mut table = format!("{:path_width$}  {:7}", "PATH", "MACHINE");
+
+    for (_, title) in COLUMNS {
+        table.push_str(&format!("  {title}"));
+    }
+
+    table.push('\n');
+
+    for file in audit.members() {
+        let path = file["path"].as_str().unwrap_or("");
+
+        if let Some(error) = file["error"].as_str() {
+            table.push_str(&format!("{path:path_width$}  {error}\n"));
+            continue;
+        }
+
+        table.push_str(&format!(
+            "{:path_width$}  {:7}",
+            path,
+            file["machine"].as_str().unwrap_or("")
+        ));
+
+        for (name, title) in COLUMNS {
+            let value = match file[*name].as_bool() {
+                Some(true) => "yes",
+                Some(false) => "no",
+                None => "-",
+            };
+
+            table.push_str(&format!("  {value:width$}", width = title.len()));
+        }
+
+        table.truncate(table.trim_end().len());
+        table.push('\n');
+    }
+
+    return table;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pe::test_image::{ImageBuilder, LOAD_CONFIG_DIRECTORY};
+    use super::super::pe::{IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_I386};
+    use super::*;
+
+    /*
+     * Load config holding the given (offset, value) fields, the pointer sized ones taking 4 or 8
+     * bytes depending on the bitness of the image
+     */
+    fn add_load_config(image: &mut ImageBuilder, fields: &[(usize, u64)]) {
+        let (size, pointer_size) = match image.is_32_bits() {
+            true => (0x80usize, 4),
+            false => (0xd0usize, 8),
+        };
+
+        let mut load_config = vec![0u8; size];
+        load_config[0..4].copy_from_slice(&(size as u32).to_le_bytes());
+
+        for (offset, value) in fields {
+            load_config[*offset..*offset + pointer_size]
+                .copy_from_slice(&value.to_le_bytes()[..pointer_size]);
+        }
+
+        let rva = image.append(&load_config);
+        image.set_directory(LOAD_CONFIG_DIRECTORY, rva, size as u32);
+    }
+
+    fn audit_image(image: &ImageBuilder) -> Mitigations {
+        let pe = pe::parse_pe_data(pe::PEData::Owned(image.build())).unwrap();
+
+        return Mitigations::from_pe(&pe);
+    }
+
+    #[test]
+    fn stripped_relocations_disable_dynamic_base() {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.set_dll_characteristics(DllCharacteristics::DYNAMIC_BASE.bits());
+
+        assert!(audit_image(&image).dynamic_base);
+
+        image.set_characteristics(
+            (Characteristics::EXECUTABLE_IMAGE | Characteristics::RELOCS_STRIPPED).bits(),
+        );
+
+        let mitigations = audit_image(&image);
+
+        assert!(!mitigations.dynamic_base);
+        assert!(mitigations.get_missing().contains(&"dynamic_base"));
+    }
+
+    #[test]
+    fn high_entropy_va_only_applies_to_64_bit_images() {
+        let dll_characteristics =
+            (DllCharacteristics::DYNAMIC_BASE | DllCharacteristics::HIGH_ENTROPY_VA).bits();
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.set_dll_characteristics(dll_characteristics);
+
+        assert_eq!(audit_image(&image).high_entropy_va, Some(true));
+
+        /* Without ASLR, the bit has no effect */
+        image.set_dll_characteristics(DllCharacteristics::HIGH_ENTROPY_VA.bits());
+
+        assert_eq!(audit_image(&image).high_entropy_va, Some(false));
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_I386);
+        image.set_dll_characteristics(dll_characteristics);
+
+        let mitigations = audit_image(&image);
+
+        assert_eq!(mitigations.high_entropy_va, None);
+        assert!(!mitigations.get_missing().contains(&"high_entropy_va"));
+    }
+
+    #[test]
+    fn cfg_needs_an_instrumented_image() {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.set_dll_characteristics(DllCharacteristics::GUARD_CF.bits());
+
+        /* The flag alone, without a load config */
+        assert!(!audit_image(&image).cfg);
+
+        add_load_config(&mut image, &[(0x90, 0x500)]);
+
+        assert!(audit_image(&image).cfg);
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.set_dll_characteristics(DllCharacteristics::GUARD_CF.bits());
+        add_load_config(&mut image, &[(0x90, 0x400)]);
+
+        assert!(!audit_image(&image).cfg);
+
+        /* The guard flags alone */
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        add_load_config(&mut image, &[(0x90, 0x100)]);
+
+        assert!(!audit_image(&image).cfg);
+    }
+
+    #[test]
+    fn safe_seh_only_applies_to_x86_images() {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_I386);
+
+        assert_eq!(audit_image(&image).safe_seh, Some(false));
+
+        image.set_dll_characteristics(DllCharacteristics::NO_SEH.bits());
+
+        assert_eq!(audit_image(&image).safe_seh, Some(true));
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_I386);
+        add_load_config(&mut image, &[(0x40, 0x10002000), (0x44, 2)]);
+
+        assert_eq!(audit_image(&image).safe_seh, Some(true));
+
+        let image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        let mitigations = audit_image(&image);
+
+        assert_eq!(mitigations.safe_seh, None);
+        assert!(mitigations.to_json()["safe_seh"].is_null());
+    }
+
+    #[test]
+    fn gs_needs_a_security_cookie() {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+
+        assert!(!audit_image(&image).gs);
+
+        add_load_config(&mut image, &[(0x58, 0x180004000)]);
+
+        assert!(audit_image(&image).gs);
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_I386);
+        add_load_config(&mut image, &[(0x3c, 0x10004000)]);
+
+        assert!(audit_image(&image).gs);
+
+        /* A load config without a cookie */
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        add_load_config(&mut image, &[]);
+
+        assert!(!audit_image(&image).gs);
+    }
+
+    #[test]
+    fn cet_compatibility_comes_from_the_extended_dll_characteristics() {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+
+        assert!(!audit_image(&image).cet_compatible);
+
+        image.add_debug_entry(
+            pe::IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS,
+            &pe::IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT.to_le_bytes(),
+        );
+
+        assert!(audit_image(&image).cet_compatible);
+
+        /* Extended characteristics without the CET bit */
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.add_debug_entry(
+            pe::IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS,
+            &0x40u32.to_le_bytes(),
+        );
+
+        assert!(!audit_image(&image).cet_compatible);
+
+        /* Other debug entries are not looked at */
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.add_debug_entry(2, &pe::IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT.to_le_bytes());
+
+        assert!(!audit_image(&image).cet_compatible);
+    }
+
+    #[test]
+    fn optional_mitigations_are_not_missing() {
+        let mut mitigations = Mitigations::new();
+        mitigations.machine = Machine::Amd64;
+        mitigations.dynamic_base = true;
+        mitigations.high_entropy_va = Some(false);
+        mitigations.nx_compat = true;
+        mitigations.gs = true;
+
+        assert_eq!(mitigations.get_missing(), ["high_entropy_va", "cfg"]);
+        assert_eq!(
+            mitigations.get_missing_optional(),
+            ["cet_compatible", "force_integrity"]
+        );
+
+        mitigations.cet_compatible = true;
+
+        let result = mitigations.to_json();
+
+        assert_eq!(result["missing"].len(), 2);
+        assert_eq!(result["missing_optional"][0], "force_integrity");
+        assert!(result["safe_seh"].is_null());
+    }
+}
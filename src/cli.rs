@@ -52,10 +52,10 @@ impl CLIArg {
         arg_action: CLIArgAction,
     ) -> CLIArg {
         return CLIArg {
-            arg_name: arg_name,
-            arg_short_name: arg_short_name,
-            arg_type: arg_type,
-            arg_action: arg_action,
+            arg_name,
+            arg_short_name,
+            arg_type,
+            arg_action,
             arg_value: String::default(),
         };
     }
@@ -68,6 +68,7 @@ impl CLIArg {
 pub struct CLIParser {
     args: HashMap<&'static str, CLIArg>,
     short_names: HashMap<&'static str, &'static str>,
+    positional_args: Vec<String>,
 }
 
 impl CLIParser {
@@ -93,25 +94,33 @@ impl CLIParser {
             CLIArg::new(arg_name, arg_short_name, arg_type, arg_action),
         );
 
-        if arg_short_name.is_some() {
+        if let Some(short_name) = arg_short_name {
             self.short_names
-                .insert(arg_short_name.unwrap().trim_matches('-'), arg_name);
+                .insert(short_name.trim_matches('-'), arg_name);
         }
 
         return Ok(());
     }
 
     pub fn parse(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let args = std::env::args();
+        return self.parse_args(std::env::args().skip(1));
+    }
 
-        if args.len() == 1 {
-            return Ok(());
-        }
+    /* Parses the arguments that follow the program name */
+    fn parse_args(
+        &mut self,
+        args: impl Iterator<Item = String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        for arg in args {
+            /* Arguments that are not options (commands, files...) are kept in order */
+            if !arg.starts_with('-') {
+                self.positional_args.push(arg);
+                continue;
+            }
 
-        for arg in args.skip(1).into_iter() {
-            let first_eq = arg.find("=").unwrap_or(usize::max_value());
+            let first_eq = arg.find("=").unwrap_or(usize::MAX);
 
-            if first_eq != usize::max_value() {
+            if first_eq != usize::MAX {
                 let arg_split = arg
                     .split_once("=")
                     .expect("Can't find any '=' in the argument but should");
@@ -158,6 +167,10 @@ impl CLIParser {
         return Ok(());
     }
 
+    pub fn get_positional_arguments(&self) -> &[String] {
+        return &self.positional_args;
+    }
+
     pub fn get_argument_as_i64(&self, arg_name: &str) -> Result<i64, Box<dyn std::error::Error>> {
         let arg = self.args.get(arg_name).expect("Cannot find argument");
 
@@ -241,3 +254,66 @@ impl CLIParser {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positional_arguments_are_kept_in_order_between_options() {
+        let mut arg_parser = CLIParser::new();
+
+        arg_parser
+            .add_argument("--format", None, CLIArgType::String, CLIArgAction::Store)
+            .unwrap();
+        arg_parser
+            .add_argument(
+                "--recurse",
+                Some("-r"),
+                CLIArgType::Bool,
+                CLIArgAction::StoreTrue,
+            )
+            .unwrap();
+        arg_parser
+            .add_argument(
+                "--apiset-schema",
+                None,
+                CLIArgType::String,
+                CLIArgAction::Store,
+            )
+            .unwrap();
+
+        let args = [
+            "apiset",
+            "--format=table",
+            "diff",
+            "-r",
+            "old/apisetschema.dll",
+            "--apiset-schema=C:\\a=b\\apisetschema.dll",
+            "new/apisetschema.dll",
+        ];
+
+        arg_parser
+            .parse_args(args.iter().map(|arg| arg.to_string()))
+            .unwrap();
+
+        assert_eq!(
+            arg_parser.get_positional_arguments(),
+            [
+                "apiset",
+                "diff",
+                "old/apisetschema.dll",
+                "new/apisetschema.dll"
+            ]
+        );
+        assert_eq!(
+            arg_parser.get_argument_as_string("format").unwrap(),
+            "table"
+        );
+        assert!(arg_parser.get_argument_as_bool_with_default("recurse", false));
+        assert_eq!(
+            arg_parser.get_argument_as_string("apiset-schema").unwrap(),
+            "C:\\a=b\\apisetschema.dll"
+        );
+    }
+}
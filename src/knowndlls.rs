@@ -0,0 +1,273 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+/*
+ * Known DLLs are mapped by the loader from the \KnownDlls object directory, created at boot from
+ * the System32 files listed under this registry key. They cannot be loaded from anywhere else
+ */
+pub const KNOWN_DLLS_KEY_NAME: &str = "KnownDLLs";
+
+/* Values of the KnownDLLs key of Windows 10 (x64) */
+const DEFAULT_KNOWN_DLLS: &[&str] = &[
+    "advapi32.dll",
+    "clbcatq.dll",
+    "combase.dll",
+    "comdlg32.dll",
+    "coml2.dll",
+    "difxapi.dll",
+    "gdi32.dll",
+    "gdiplus.dll",
+    "imagehlp.dll",
+    "imm32.dll",
+    "kernel32.dll",
+    "msctf.dll",
+    "msvcrt.dll",
+    "normaliz.dll",
+    "nsi.dll",
+    "ole32.dll",
+    "oleaut32.dll",
+    "psapi.dll",
+    "rpcrt4.dll",
+    "sechost.dll",
+    "setupapi.dll",
+    "shcore.dll",
+    "shell32.dll",
+    "shlwapi.dll",
+    "user32.dll",
+    "wldap32.dll",
+    "wow64.dll",
+    "wow64cpu.dll",
+    "wow64win.dll",
+    "wowarmhw.dll",
+    "ws2_32.dll",
+    "xtajit.dll",
+];
+
+/* Not listed in the registry, but always mapped first from System32 */
+const NTDLL_NAME: &str = "ntdll.dll";
+
+#[derive(Default, Clone, Debug)]
+pub struct KnownDlls {
+    names: HashSet<String>,
+}
+
+impl KnownDlls {
+    pub fn new() -> KnownDlls {
+        return KnownDlls::default();
+    }
+
+    pub fn from_names<'a>(names: impl IntoIterator<Item = &'a str>) -> KnownDlls {
+        let mut known_dlls = KnownDlls::new();
+
+        for name in names {
+            known_dlls.names.insert(name.trim().to_ascii_lowercase());
+        }
+
+        known_dlls.names.insert(NTDLL_NAME.to_string());
+
+        return known_dlls;
+    }
+
+    /*
+     * List of names, one per line. Empty lines and lines starting with '#' or ';' are ignored
+     */
+    pub fn from_list(list: &str) -> KnownDlls {
+        return KnownDlls::from_names(
+            list.lines().map(str::trim).filter(|line| {
+                !line.is_empty() && !line.starts_with('#') && !line.starts_with(';')
+            }),
+        );
+    }
+
+    /*
+     * Registry export (.reg) of the KnownDLLs key, e.g.
+     * [HKEY_LOCAL_MACHINE\SYSTEM\CurrentControlSet\Control\Session Manager\KnownDLLs]. The key of
+     * an offline hive can have any path, only its name is checked. The DllDirectory values and
+     * the values that are not strings are skipped
+     */
+    pub fn from_reg(reg: &str) -> Result<KnownDlls, Box<dyn std::error::Error>> {
+        let mut names: Vec<String> = Vec::new();
+        let mut found_key = false;
+        let mut in_key = false;
+
+        for line in reg.lines().map(str::trim) {
+            if let Some(key_name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                in_key = key_name
+                    .rsplit('\\')
+                    .next()
+                    .is_some_and(|name| name.eq_ignore_ascii_case(KNOWN_DLLS_KEY_NAME));
+                found_key |= in_key;
+                continue;
+            }
+
+            if !in_key {
+                continue;
+            }
+
+            let Some((value_name, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            let (Some(value_name), Some(value)) =
+                (parse_reg_string(value_name), parse_reg_string(value))
+            else {
+                continue;
+            };
+
+            if value_name.to_ascii_lowercase().starts_with("dlldirectory") {
+                continue;
+            }
+
+            names.push(value);
+        }
+
+        if !found_key {
+            return Err(
+                format!("Cannot find the {KNOWN_DLLS_KEY_NAME} key in registry export").into(),
+            );
+        }
+
+        return Ok(KnownDlls::from_names(names.iter().map(String::as_str)));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        return self.names.contains(&name.to_ascii_lowercase());
+    }
+
+    pub fn get_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.names.iter().map(String::as_str).collect();
+        names.sort();
+
+        return names;
+    }
+}
+
+/*
+ * Quoted string of a .reg file, with '\' escaping '\' and '"'
+ */
+fn parse_reg_string(value: &str) -> Option<String> {
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+
+    let mut string = String::new();
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => string.push(chars.next()?),
+            _ => string.push(c),
+        }
+    }
+
+    return Some(string);
+}
+
+pub fn load_default_known_dlls() -> KnownDlls {
+    return KnownDlls::from_names(DEFAULT_KNOWN_DLLS.iter().copied());
+}
+
+/*
+ * Loads a list of Known DLLs from a registry export (regedit writes them in UTF-16) or a plain
+ * list of names
+ */
+pub fn load_known_dlls(path: &Path) -> Result<KnownDlls, Box<dyn std::error::Error>> {
+    log::trace!("Loading Known DLLs from {}", path.display());
+
+    let data = std::fs::read(path)
+        .map_err(|err| format!("Cannot read Known DLLs \"{}\": {err}", path.display()))?;
+
+    /* Registry exports use the same encodings as manifests */
+    let text = super::manifest::decode_manifest(&data);
+
+    let first_line = text.lines().next().unwrap_or("").trim();
+
+    if first_line.starts_with("Windows Registry Editor") || first_line == "REGEDIT4" {
+        return KnownDlls::from_reg(&text);
+    }
+
+    return Ok(KnownDlls::from_list(&text));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_directory::TestDirectory;
+    use super::*;
+
+    const REG_EXPORT: &str = r#"Windows Registry Editor Version 5.00
+
+[HKEY_LOCAL_MACHINE\SYSTEM\CurrentControlSet\Control\Session Manager]
+"BootExecute"="autocheck.dll"
+
+[HKEY_LOCAL_MACHINE\SYSTEM\CurrentControlSet\Control\Session Manager\KnownDLLs]
+"_wow64cpu"="wow64cpu.dll"
+"DllDirectory"="%SystemRoot%\\system32"
+"DllDirectory32"="%SystemRoot%\\syswow64"
+"kernel32"="KERNEL32.dll"
+"quoted"="we\"ird.dll"
+"binary"=hex:01,02
+"count"=dword:00000001
+
+[HKEY_LOCAL_MACHINE\SYSTEM\CurrentControlSet\Control\Session Manager\KnownDLLs\Other]
+"other"="other.dll"
+"#;
+
+    #[test]
+    fn reg_strings_are_unescaped() {
+        assert_eq!(
+            parse_reg_string(r#" "%SystemRoot%\\system32" "#).as_deref(),
+            Some(r"%SystemRoot%\system32")
+        );
+        assert_eq!(
+            parse_reg_string(r#""we\"ird.dll""#).as_deref(),
+            Some("we\"ird.dll")
+        );
+        assert_eq!(parse_reg_string(r#""""#).as_deref(), Some(""));
+        assert!(parse_reg_string(r#""trailing\""#).is_none());
+        assert!(parse_reg_string("dword:00000001").is_none());
+    }
+
+    #[test]
+    fn known_dlls_are_read_from_the_registry_key() {
+        let known_dlls = KnownDlls::from_reg(REG_EXPORT).unwrap();
+
+        assert_eq!(
+            known_dlls.get_names(),
+            ["kernel32.dll", "ntdll.dll", "we\"ird.dll", "wow64cpu.dll"]
+        );
+    }
+
+    #[test]
+    fn reg_exports_without_the_key_are_rejected() {
+        let reg = "Windows Registry Editor Version 5.00\n\n[HKEY_LOCAL_MACHINE\\SOFTWARE\\KnownDLLsBackup]\n\"a\"=\"a.dll\"\n";
+
+        assert!(KnownDlls::from_reg(reg).is_err());
+    }
+
+    #[test]
+    fn utf16_reg_exports_are_loaded() {
+        let directory = TestDirectory::new("knowndlls");
+
+        let mut data: Vec<u8> = vec![0xff, 0xfe];
+
+        for c in REG_EXPORT.replace('\n', "\r\n").encode_utf16() {
+            data.extend_from_slice(&c.to_le_bytes());
+        }
+
+        let known_dlls = load_known_dlls(&directory.write("knowndlls.reg", data)).unwrap();
+
+        assert!(known_dlls.contains("Kernel32.dll"));
+        assert!(known_dlls.contains("ntdll.dll"));
+        assert!(!known_dlls.contains("other.dll"));
+        assert!(!known_dlls.contains("autocheck.dll"));
+    }
+
+    #[test]
+    fn plain_lists_skip_comments() {
+        let known_dlls =
+            KnownDlls::from_list("# Known DLLs\n\nuser32.dll\n; gdi32.dll\n  shell32.dll  \n");
+
+        assert_eq!(
+            known_dlls.get_names(),
+            ["ntdll.dll", "shell32.dll", "user32.dll"]
+        );
+    }
+}
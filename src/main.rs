@@ -1,10 +1,259 @@
-use log;
-use std::{path::PathBuf, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 pub mod apiset;
+pub mod audit;
 pub mod cli;
+pub mod knowndlls;
+pub mod manifest;
 pub mod pe;
 pub mod search;
+pub mod sxs;
+#[cfg(test)]
+pub mod test_directory;
+
+/*
+ * Loads the Known DLLs from a list or a registry export, "none" to disable them, or an empty string
+ * for the built-in list
+ */
+fn load_known_dlls(
+    known_dlls_path: &str,
+) -> Result<knowndlls::KnownDlls, Box<dyn std::error::Error>> {
+    return match known_dlls_path {
+        "none" => Ok(knowndlls::KnownDlls::new()),
+        "" => Ok(knowndlls::load_default_known_dlls()),
+        path => knowndlls::load_known_dlls(Path::new(path)),
+    };
+}
+
+/*
+ * Loads the API Set schema from a path, "embedded" for the snapshot shipped with fdw, or an empty
+ * string for the one of the Windows directory
+ */
+fn load_apiset_schema(
+    schema_path: &str,
+    search_order: &search::SearchOrder,
+) -> Result<apiset::APISet, Box<dyn std::error::Error>> {
+    return match schema_path {
+        "embedded" => apiset::load_embedded_apisetschema_mapping(),
+        "" => match search_order.get_system32_directory() {
+            Some(system32_directory) => apiset::load_apisetschema_mapping(
+                &search::find_directory_entry(&system32_directory, apiset::API_SET_SCHEMA_DLL_NAME)
+                    .unwrap_or(system32_directory.join(apiset::API_SET_SCHEMA_DLL_NAME)),
+            ),
+            None => {
+                log::info!(
+                    "No Windows directory available, using the embedded apisetschema mapping"
+                );
+                apiset::load_embedded_apisetschema_mapping()
+            }
+        },
+        path => apiset::load_apisetschema_mapping(Path::new(path)),
+    };
+}
+
+/*
+ * fdw apiset [<schema>] [<other schema>]: dumps a schema, or the differences between two schemas
+ */
+fn apiset_command(
+    arg_parser: &cli::CLIParser,
+    schema_paths: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut search_order = search::SearchOrder::new(None, true);
+
+    let windows_root = arg_parser.get_argument_as_string("windows-root").unwrap();
+
+    if !windows_root.is_empty() {
+        search_order.windows_root = Some(PathBuf::from(windows_root));
+    }
+
+    let as_table = match arg_parser
+        .get_argument_as_string("format")
+        .unwrap()
+        .as_str()
+    {
+        "" | "json" => false,
+        "table" => true,
+        format => return Err(format!("Unknown output format \"{format}\"").into()),
+    };
+
+    match schema_paths {
+        [] | [_] => {
+            let schema_path = match schema_paths.first() {
+                Some(schema_path) => schema_path.clone(),
+                None => arg_parser.get_argument_as_string("apiset-schema").unwrap(),
+            };
+
+            let apiset_schema = load_apiset_schema(&schema_path, &search_order)?;
+
+            if as_table {
+                print!("{}", apiset_schema.to_table());
+            } else {
+                println!("{:#}", apiset_schema.to_json());
+            }
+        }
+        [old_schema_path, new_schema_path] => {
+            let old_apiset_schema = load_apiset_schema(old_schema_path, &search_order)?;
+            let new_apiset_schema = load_apiset_schema(new_schema_path, &search_order)?;
+
+            if as_table {
+                print!(
+                    "{}",
+                    apiset::diff_apisets_to_table(&old_apiset_schema, &new_apiset_schema)
+                );
+            } else {
+                println!(
+                    "{:#}",
+                    apiset::diff_apisets(&old_apiset_schema, &new_apiset_schema)
+                );
+            }
+        }
+        _ => return Err("fdw apiset expects at most two schemas to compare".into()),
+    }
+
+    return Ok(());
+}
+
+/*
+ * Walks the dependencies of a file with the search order, API Set schema and Known DLLs set up
+ * from the command line
+ */
+fn resolve_file_dependencies(
+    arg_parser: &cli::CLIParser,
+    file_path: &str,
+    recurse: bool,
+    verify_symbols: bool,
+) -> Result<search::DependencyWalk, Box<dyn std::error::Error>> {
+    if arg_parser.get_argument_as_bool_with_default("check-extension", false)
+        && !pe::has_pe_extension(Path::new(file_path))
+    {
+        return Err(pe::PeError::UnsupportedExtension {
+            path: PathBuf::from(file_path),
+        }
+        .into());
+    }
+
+    log::trace!("Initializing search order");
+
+    let application_directory = match Path::new(file_path).parent() {
+        Some(parent) if parent.as_os_str().is_empty() => Some(PathBuf::from(".")),
+        Some(parent) => Some(parent.to_path_buf()),
+        None => None,
+    };
+
+    let mut search_order = search::SearchOrder::new(
+        application_directory,
+        !arg_parser.get_argument_as_bool_with_default("unsafe-dll-search", false),
+    );
+
+    let custom_search_order = arg_parser.get_argument_as_string("search-order").unwrap();
+
+    if !custom_search_order.is_empty() {
+        search_order.locations.clear();
+
+        for location_name in custom_search_order.split(",") {
+            search_order.locations.push(
+                search::SearchLocation::from_name(location_name)
+                    .ok_or(format!("Unknown search location \"{location_name}\""))?,
+            );
+        }
+    }
+
+    let windows_root = arg_parser.get_argument_as_string("windows-root").unwrap();
+
+    if !windows_root.is_empty() {
+        search_order.windows_root = Some(PathBuf::from(windows_root));
+    }
+
+    let winsxs_directory = arg_parser.get_argument_as_string("winsxs").unwrap();
+
+    if !winsxs_directory.is_empty() {
+        search_order.winsxs_directory = Some(PathBuf::from(winsxs_directory));
+    }
+
+    let current_directory = arg_parser.get_argument_as_string("current-dir").unwrap();
+
+    if !current_directory.is_empty() {
+        search_order.current_directory = Some(PathBuf::from(current_directory));
+    }
+
+    let user_search_paths = arg_parser.get_argument_as_string("search-paths").unwrap();
+
+    if !user_search_paths.is_empty() {
+        for path in user_search_paths.split(";") {
+            search_order.user_directories.push(
+                PathBuf::from_str(path)
+                    .expect("Cannot convert a path from --search-path into a PathBuf"),
+            );
+        }
+    }
+
+    log::trace!("Search order: {:?}", search_order);
+
+    let apiset_schema_path = arg_parser.get_argument_as_string("apiset-schema").unwrap();
+
+    let apiset_schema_mapping = load_apiset_schema(&apiset_schema_path, &search_order);
+
+    let apiset_schema_mapping = match apiset_schema_mapping {
+        Ok(mapping) => mapping,
+        Err(err) if arg_parser.get_argument_as_bool_with_default("apiset-optional", false) => {
+            log::warn!("{err}, API Set dlls will be left unresolved");
+            apiset::APISet::new()
+        }
+        Err(err) => return Err(err),
+    };
+
+    let known_dlls = load_known_dlls(&arg_parser.get_argument_as_string("known-dlls").unwrap())?;
+
+    return search::resolve_dependencies(
+        PathBuf::from_str(file_path).expect("Cannot convert file path to PathBuf"),
+        &search_order,
+        apiset_schema_mapping,
+        known_dlls,
+        recurse,
+        verify_symbols,
+    );
+}
+
+/*
+ * fdw audit [<file>]: security mitigations of a file and of every dll of its dependency tree
+ */
+fn audit_command(
+    arg_parser: &cli::CLIParser,
+    file_paths: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file_path = match file_paths {
+        [] => arg_parser
+            .get_argument_as_string("file")
+            .expect("Argument file has not been passed"),
+        [file_path] => file_path.clone(),
+        _ => return Err("fdw audit expects a single file".into()),
+    };
+
+    let as_table = match arg_parser
+        .get_argument_as_string("format")
+        .unwrap()
+        .as_str()
+    {
+        "" | "json" => false,
+        "table" => true,
+        format => return Err(format!("Unknown output format \"{format}\"").into()),
+    };
+
+    let walk = resolve_file_dependencies(arg_parser, &file_path, true, false)?;
+
+    let audit = audit::audit_dependencies(&walk.files);
+
+    if as_table {
+        print!("{}", audit::audit_to_table(&audit));
+    } else {
+        println!("{:#}", audit);
+    }
+
+    return Ok(());
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut arg_parser = cli::CLIParser::new();
@@ -42,6 +291,99 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .expect("Error while adding argument to CLIParser");
 
+    arg_parser
+        .add_argument(
+            "--verify-symbols",
+            None,
+            cli::CLIArgType::Bool,
+            cli::CLIArgAction::StoreTrue,
+        )
+        .expect("Error while adding argument to CLIParser");
+
+    arg_parser
+        .add_argument(
+            "--check-extension",
+            None,
+            cli::CLIArgType::Bool,
+            cli::CLIArgAction::StoreTrue,
+        )
+        .expect("Error while adding argument to CLIParser");
+
+    arg_parser
+        .add_argument(
+            "--windows-root",
+            None,
+            cli::CLIArgType::String,
+            cli::CLIArgAction::Store,
+        )
+        .expect("Error while adding argument to CLIParser");
+    arg_parser
+        .add_argument(
+            "--current-dir",
+            None,
+            cli::CLIArgType::String,
+            cli::CLIArgAction::Store,
+        )
+        .expect("Error while adding argument to CLIParser");
+    arg_parser
+        .add_argument(
+            "--search-order",
+            None,
+            cli::CLIArgType::String,
+            cli::CLIArgAction::Store,
+        )
+        .expect("Error while adding argument to CLIParser");
+    arg_parser
+        .add_argument(
+            "--unsafe-dll-search",
+            None,
+            cli::CLIArgType::Bool,
+            cli::CLIArgAction::StoreTrue,
+        )
+        .expect("Error while adding argument to CLIParser");
+
+    arg_parser
+        .add_argument(
+            "--winsxs",
+            None,
+            cli::CLIArgType::String,
+            cli::CLIArgAction::Store,
+        )
+        .expect("Error while adding argument to CLIParser");
+
+    arg_parser
+        .add_argument(
+            "--apiset-schema",
+            None,
+            cli::CLIArgType::String,
+            cli::CLIArgAction::Store,
+        )
+        .expect("Error while adding argument to CLIParser");
+    arg_parser
+        .add_argument(
+            "--apiset-optional",
+            None,
+            cli::CLIArgType::Bool,
+            cli::CLIArgAction::StoreTrue,
+        )
+        .expect("Error while adding argument to CLIParser");
+    arg_parser
+        .add_argument(
+            "--known-dlls",
+            None,
+            cli::CLIArgType::String,
+            cli::CLIArgAction::Store,
+        )
+        .expect("Error while adding argument to CLIParser");
+    arg_parser
+        .add_argument(
+            "--format",
+            None,
+            cli::CLIArgType::String,
+            cli::CLIArgAction::Store,
+        )
+        .expect("Error while adding argument to CLIParser");
+
     arg_parser
         .parse()
         .expect("Error caught while parsing arguments");
@@ -60,77 +402,27 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     log::trace!("Starting fdw");
 
-    let apiset_schema_mapping = apiset::load_apisetschema_mapping()?;
+    let positional_args = arg_parser.get_positional_arguments().to_vec();
+
+    match positional_args.first().map(String::as_str) {
+        Some("apiset") => return apiset_command(&arg_parser, &positional_args[1..]),
+        Some("audit") => return audit_command(&arg_parser, &positional_args[1..]),
+        Some(command) => return Err(format!("Unknown command \"{command}\"").into()),
+        None => {}
+    }
 
     let file_path = arg_parser
         .get_argument_as_string("file")
         .expect("Argument file has not been passed");
 
-    log::trace!("Initializing search paths");
-
-    let mut search_paths: Vec<PathBuf> = Vec::new();
-
-    search_paths.push(
-        PathBuf::from_str(file_path.as_str())
-            .expect("Cannot convert path from --file into a PathBuf")
-            .parent()
-            .expect("Cannot find parent of --file")
-            .to_path_buf(),
-    );
-
-    log::trace!("pe directory added to search paths: {:?}", search_paths);
-
-    for path in std::env::split_paths(
-        std::env::var("PATH")
-            .expect("Cannot get the value of PATH environment variable")
-            .as_str(),
-    ) {
-        if !path.exists() {
-            println!(
-                "Warning: Path \"{}\" not found, discarding it",
-                path.display()
-            );
-            continue;
-        }
-
-        search_paths.push(path);
-    }
-
-    log::trace!("PATH paths added to search paths: {:?}", search_paths);
-
-    let user_search_paths = arg_parser.get_argument_as_string("search-paths").unwrap();
-
-    if !user_search_paths.is_empty() {
-        for path in user_search_paths.split(";") {
-            let path_buf = PathBuf::from_str(path)
-                .expect("Cannot convert a path from --search-path into a PathBuf");
-
-            if !path_buf.exists() {
-                println!(
-                    "Warning: Path \"{}\" not found, discarding it",
-                    path_buf.display()
-                );
-                continue;
-            }
-
-            search_paths.push(path_buf);
-        }
-
-        log::trace!(
-            "User provided paths added to search paths: {:?}",
-            search_paths
-        );
-    }
-
-    match search::resolve_dependencies(
-        PathBuf::from_str(file_path.as_str()).expect("Cannot convert file path to PathBuf"),
-        search_paths,
-        apiset_schema_mapping,
+    let walk = resolve_file_dependencies(
+        &arg_parser,
+        &file_path,
         arg_parser.get_argument_as_bool_with_default("recurse", false),
-    ) {
-        Ok(dependencies) => println!("{:#}", dependencies),
-        Err(err) => return Err(err),
-    };
+        arg_parser.get_argument_as_bool_with_default("verify-symbols", false),
+    )?;
+
+    println!("{:#}", walk.dependencies);
 
     return Ok(());
 }
@@ -0,0 +1,722 @@
+/*
+ * Application and assembly manifests
+ * https://learn.microsoft.com/en-us/windows/win32/sbscs/manifest-file-schema
+ */
+
+/*
+ * XML element found while scanning a document. Only start and empty element tags are reported,
+ * with the local names (without namespace prefix) of their ancestors
+ */
+#[derive(Default, Clone, Debug)]
+pub struct XmlElement {
+    pub ancestors: Vec<String>,
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+}
+
+impl XmlElement {
+    pub fn get_attribute(&self, name: &str) -> Option<&str> {
+        return self
+            .attributes
+            .iter()
+            .find(|(attribute_name, _)| attribute_name.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str());
+    }
+
+    pub fn get_parent(&self) -> Option<&str> {
+        return self.ancestors.last().map(String::as_str);
+    }
+}
+
+fn get_local_name(name: &str) -> &str {
+    return match name.rsplit_once(':') {
+        Some((_, local_name)) => local_name,
+        None => name,
+    };
+}
+
+fn decode_entities(value: &str) -> String {
+    return value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+}
+
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes: Vec<(String, String)> = Vec::new();
+    let mut rest = tag;
+
+    while let Some(equal_position) = rest.find('=') {
+        let attribute_name = rest[..equal_position].trim();
+        let value_start = rest[equal_position + 1..].trim_start();
+
+        let quote = match value_start.chars().next() {
+            Some(c) if c == '"' || c == '\'' => c,
+            _ => break,
+        };
+
+        let value_end = match value_start[1..].find(quote) {
+            Some(position) => position + 1,
+            None => break,
+        };
+
+        attributes.push((
+            get_local_name(attribute_name).to_string(),
+            decode_entities(&value_start[1..value_end]),
+        ));
+
+        rest = &value_start[value_end + 1..];
+    }
+
+    return attributes;
+}
+
+/*
+ * Minimal XML scanner, enough for manifests: comments, processing instructions, CDATA sections and
+ * text are skipped. Malformed documents are scanned as far as possible
+ */
+pub fn scan_xml_elements(document: &str) -> Vec<XmlElement> {
+    let mut elements: Vec<XmlElement> = Vec::new();
+    let mut ancestors: Vec<String> = Vec::new();
+    let mut rest = document;
+
+    while let Some(tag_start) = rest.find('<') {
+        rest = &rest[tag_start..];
+
+        let tag_end_marker = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<![CDATA[") {
+            "]]>"
+        } else if rest.starts_with("<?") {
+            "?>"
+        } else {
+            ">"
+        };
+
+        let tag_end = match rest.find(tag_end_marker) {
+            Some(position) => position,
+            None => break,
+        };
+
+        let tag = &rest[1..tag_end];
+
+        rest = &rest[tag_end + tag_end_marker.len()..];
+
+        if tag_end_marker != ">" || tag.starts_with('!') {
+            continue;
+        }
+
+        if let Some(closing_name) = tag.strip_prefix('/') {
+            let closing_name = get_local_name(closing_name.trim());
+
+            if let Some(position) = ancestors.iter().rposition(|name| name == closing_name) {
+                ancestors.truncate(position);
+            }
+
+            continue;
+        }
+
+        let is_empty_element = tag.ends_with('/');
+        let tag = tag.trim_end_matches('/');
+
+        let name_end = tag.find(char::is_whitespace).unwrap_or(tag.len());
+        let name = get_local_name(&tag[..name_end]).to_string();
+
+        elements.push(XmlElement {
+            ancestors: ancestors.clone(),
+            name: name.clone(),
+            attributes: parse_attributes(&tag[name_end..]),
+        });
+
+        if !is_empty_element {
+            ancestors.push(name);
+        }
+    }
+
+    return elements;
+}
+
+/*
+ * Manifests are usually UTF-8, but can also be UTF-16 with a byte order mark
+ */
+pub fn decode_manifest(data: &[u8]) -> String {
+    if let Some(utf16_data) = data.strip_prefix(&[0xff, 0xfe]) {
+        let utf16: Vec<u16> = utf16_data
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+
+        return String::from_utf16_lossy(&utf16);
+    }
+
+    if let Some(utf16_data) = data.strip_prefix(&[0xfe, 0xff]) {
+        let utf16: Vec<u16> = utf16_data
+            .chunks_exact(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+
+        return String::from_utf16_lossy(&utf16);
+    }
+
+    let data = data.strip_prefix(&[0xef, 0xbb, 0xbf]).unwrap_or(data);
+
+    return String::from_utf8_lossy(data).into_owned();
+}
+
+/*
+ * Assembly versions are made of four 16 bits numbers (major.minor.build.revision)
+ */
+pub fn parse_assembly_version(version: &str) -> Option<[u16; 4]> {
+    let mut numbers = [0u16; 4];
+    let mut components = version.trim().split('.');
+
+    for number in numbers.iter_mut() {
+        *number = components.next()?.parse::<u16>().ok()?;
+    }
+
+    if components.next().is_some() {
+        return None;
+    }
+
+    return Some(numbers);
+}
+
+fn is_same_version(version: &str, other: &str) -> bool {
+    return match (
+        parse_assembly_version(version),
+        parse_assembly_version(other),
+    ) {
+        (Some(version), Some(other)) => version == other,
+        _ => version.eq_ignore_ascii_case(other),
+    };
+}
+
+/* WinSxS uses "none" for assemblies that are not localized */
+fn is_neutral_language(language: &str) -> bool {
+    return language.is_empty()
+        || language.eq_ignore_ascii_case("neutral")
+        || language.eq_ignore_ascii_case("none");
+}
+
+/*
+ * Identity of an assembly (<assemblyIdentity> element)
+ */
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct AssemblyIdentity {
+    pub name: String,
+    pub version: String,
+    pub processor_architecture: String,
+    pub public_key_token: String,
+    pub language: String,
+    pub assembly_type: String,
+}
+
+impl AssemblyIdentity {
+    pub fn new() -> AssemblyIdentity {
+        return AssemblyIdentity::default();
+    }
+
+    pub fn from_element(element: &XmlElement) -> AssemblyIdentity {
+        let attribute = |name: &str| element.get_attribute(name).unwrap_or("").to_string();
+
+        return AssemblyIdentity {
+            name: attribute("name"),
+            version: attribute("version"),
+            processor_architecture: attribute("processorArchitecture"),
+            public_key_token: attribute("publicKeyToken"),
+            language: attribute("language"),
+            assembly_type: attribute("type"),
+        };
+    }
+
+    pub fn is_neutral(&self) -> bool {
+        return is_neutral_language(&self.language);
+    }
+
+    /*
+     * Checks that the identity of an assembly satisfies a reference to it (from the dependencies
+     * of a manifest). Attributes left empty in the reference, "*" for the processor architecture
+     * and the language, match any value
+     */
+    pub fn matches(&self, reference: &AssemblyIdentity) -> bool {
+        let matches_attribute = |value: &str, reference_value: &str| {
+            return reference_value.is_empty()
+                || reference_value == "*"
+                || value == "*"
+                || value.eq_ignore_ascii_case(reference_value);
+        };
+
+        let matches_language = reference.language == "*"
+            || self.language == "*"
+            || (self.is_neutral() && reference.is_neutral())
+            || self.language.eq_ignore_ascii_case(&reference.language);
+
+        /* Private assemblies do not have to be signed */
+        let matches_public_key_token = self.public_key_token.is_empty()
+            || reference.public_key_token.is_empty()
+            || self
+                .public_key_token
+                .eq_ignore_ascii_case(&reference.public_key_token);
+
+        return self.name.eq_ignore_ascii_case(&reference.name)
+            && (reference.version.is_empty()
+                || is_same_version(&self.version, &reference.version))
+            && matches_attribute(
+                &self.processor_architecture,
+                &reference.processor_architecture,
+            )
+            && matches_public_key_token
+            && matches_language;
+    }
+
+    pub fn to_json(&self) -> json::JsonValue {
+        return json::object! {
+            name: self.name.as_str(),
+            version: self.version.as_str(),
+            processor_architecture: self.processor_architecture.as_str(),
+            public_key_token: self.public_key_token.as_str(),
+            language: self.language.as_str(),
+        };
+    }
+}
+
+impl std::fmt::Display for AssemblyIdentity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{} {}", self.name, self.version);
+    }
+}
+
+/*
+ * Redirection of a range of versions of an assembly to another one (<bindingRedirect> element of
+ * publisher policies and application configuration files)
+ */
+#[derive(Clone, Debug)]
+pub struct BindingRedirect {
+    pub name: String,
+    pub old_version: ([u16; 4], [u16; 4]),
+    pub new_version: String,
+}
+
+impl BindingRedirect {
+    pub fn from_element(name: &str, element: &XmlElement) -> Option<BindingRedirect> {
+        let old_version = element.get_attribute("oldVersion")?;
+        let new_version = element.get_attribute("newVersion")?;
+
+        let old_version = match old_version.split_once('-') {
+            Some((first, last)) => (
+                parse_assembly_version(first)?,
+                parse_assembly_version(last)?,
+            ),
+            None => {
+                let version = parse_assembly_version(old_version)?;
+                (version, version)
+            }
+        };
+
+        return Some(BindingRedirect {
+            name: name.to_string(),
+            old_version,
+            new_version: new_version.trim().to_string(),
+        });
+    }
+
+    pub fn applies_to(&self, identity: &AssemblyIdentity) -> bool {
+        return self.name.eq_ignore_ascii_case(&identity.name)
+            && parse_assembly_version(&identity.version).is_some_and(|version| {
+                self.old_version.0 <= version && version <= self.old_version.1
+            });
+    }
+}
+
+/*
+ * Application manifest (the identity is then optional) or assembly manifest, listing the
+ * assemblies it depends on and, for assemblies, the files they are made of. Application
+ * configuration files (<app>.exe.config) use the same elements to redirect assemblies, to add
+ * directories where private assemblies are probed (privatePath) and to disable publisher policies
+ */
+#[derive(Default, Clone, Debug)]
+pub struct Manifest {
+    pub identity: Option<AssemblyIdentity>,
+    pub dependencies: Vec<AssemblyIdentity>,
+    pub files: Vec<String>,
+    pub binding_redirects: Vec<BindingRedirect>,
+    pub private_paths: Vec<String>,
+    pub publisher_policy_disabled: bool,
+}
+
+impl Manifest {
+    pub fn new() -> Manifest {
+        return Manifest::default();
+    }
+
+    pub fn parse(document: &str) -> Manifest {
+        let mut manifest = Manifest::new();
+
+        for element in scan_xml_elements(document) {
+            match (element.name.as_str(), element.get_parent()) {
+                ("assemblyIdentity", Some("assembly")) => {
+                    manifest.identity = Some(AssemblyIdentity::from_element(&element));
+                }
+                ("assemblyIdentity", Some("dependentAssembly")) => {
+                    manifest
+                        .dependencies
+                        .push(AssemblyIdentity::from_element(&element));
+                }
+                ("bindingRedirect", Some("dependentAssembly")) => {
+                    /* Redirections apply to the assembly of the enclosing dependentAssembly */
+                    if let Some(dependency) = manifest.dependencies.last()
+                        && let Some(binding_redirect) =
+                            BindingRedirect::from_element(&dependency.name, &element)
+                    {
+                        manifest.binding_redirects.push(binding_redirect);
+                    }
+                }
+                ("probing", _) => {
+                    if let Some(private_path) = element.get_attribute("privatePath") {
+                        manifest.private_paths.extend(
+                            private_path
+                                .split(';')
+                                .map(str::trim)
+                                .filter(|path| !path.is_empty())
+                                .map(str::to_string),
+                        );
+                    }
+                }
+                ("publisherPolicy", _) => {
+                    manifest.publisher_policy_disabled = element
+                        .get_attribute("apply")
+                        .is_some_and(|apply| apply.eq_ignore_ascii_case("no"));
+                }
+                ("file", Some("assembly")) => {
+                    if let Some(file_name) = element.get_attribute("name") {
+                        manifest.files.push(file_name.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        return manifest;
+    }
+
+    pub fn from_data(data: &[u8]) -> Manifest {
+        return Manifest::parse(&decode_manifest(data));
+    }
+
+    pub fn from_file(file_path: &std::path::Path) -> Option<Manifest> {
+        return std::fs::read(file_path)
+            .ok()
+            .map(|data| Manifest::from_data(&data));
+    }
+
+    /*
+     * Applies the first binding redirect matching an assembly reference, returning the reference
+     * to the new version
+     */
+    pub fn redirect(&self, identity: &AssemblyIdentity) -> Option<AssemblyIdentity> {
+        let binding_redirect = self
+            .binding_redirects
+            .iter()
+            .find(|binding_redirect| binding_redirect.applies_to(identity))?;
+
+        let mut redirected_identity = identity.clone();
+        redirected_identity.version = binding_redirect.new_version.clone();
+
+        return Some(redirected_identity);
+    }
+}
+
+fn get_manifest_resource(pe: &super::pe::PE, id: u16) -> Option<Manifest> {
+    let resource = pe
+        .find_resources(super::pe::RT_MANIFEST)
+        .into_iter()
+        .find(|resource| resource.name == super::pe::ResourceId::Id(id))?;
+
+    return Some(Manifest::from_data(pe.get_resource_data(resource)?));
+}
+
+/*
+ * Manifest embedded in a PE (RT_MANIFEST) that the loader uses for its static imports: the resource
+ * 1 of executables, for the whole process, and the resource 2 of dlls, for their own imports
+ */
+pub fn get_pe_manifest(pe: &super::pe::PE) -> Option<Manifest> {
+    let id = match pe
+        .get_characteristics()
+        .contains(super::pe::Characteristics::DLL)
+    {
+        true => super::pe::ISOLATIONAWARE_MANIFEST_RESOURCE_ID,
+        false => super::pe::CREATEPROCESS_MANIFEST_RESOURCE_ID,
+    };
+
+    return get_manifest_resource(pe, id);
+}
+
+/*
+ * Manifest of a private assembly shipped as a dll, always the resource 1
+ */
+pub fn get_assembly_manifest(pe: &super::pe::PE) -> Option<Manifest> {
+    return get_manifest_resource(pe, super::pe::CREATEPROCESS_MANIFEST_RESOURCE_ID);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pe::test_image::ImageBuilder;
+    use super::super::pe::{self, IMAGE_FILE_MACHINE_AMD64};
+    use super::*;
+
+    fn identity_manifest(name: &str) -> String {
+        return format!(
+            r#"<assembly><assemblyIdentity name="{name}" version="1.0.0.0"/></assembly>"#
+        );
+    }
+
+    /* Name of the embedded manifest used for static imports */
+    fn get_pe_manifest_name(characteristics: u16, ids: &[u16]) -> Option<String> {
+        let manifests: Vec<String> = ids
+            .iter()
+            .map(|id| identity_manifest(&format!("manifest{id}")))
+            .collect();
+
+        let resources: Vec<(u16, u16, &[u8])> = ids
+            .iter()
+            .zip(&manifests)
+            .map(|(id, manifest)| (pe::RT_MANIFEST, *id, manifest.as_bytes()))
+            .collect();
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.set_characteristics(characteristics);
+        image.add_resources(&resources);
+
+        let pe = pe::parse_pe_data(pe::PEData::Owned(image.build())).unwrap();
+
+        return get_pe_manifest(&pe)?.identity.map(|identity| identity.name);
+    }
+
+    #[test]
+    fn static_imports_use_the_manifest_of_the_image_type() {
+        const EXECUTABLE: u16 = 0x0022;
+        const DLL: u16 = 0x2022;
+
+        assert_eq!(
+            get_pe_manifest_name(EXECUTABLE, &[1, 2]).as_deref(),
+            Some("manifest1")
+        );
+        assert_eq!(get_pe_manifest_name(EXECUTABLE, &[2]), None);
+        assert_eq!(
+            get_pe_manifest_name(DLL, &[1, 2]).as_deref(),
+            Some("manifest2")
+        );
+        assert_eq!(get_pe_manifest_name(DLL, &[1]), None);
+
+        /* Isolation aware only manifests are not used by the loader */
+        assert_eq!(
+            get_pe_manifest_name(
+                DLL,
+                &[pe::ISOLATIONAWARE_NOSTATICIMPORT_MANIFEST_RESOURCE_ID]
+            ),
+            None
+        );
+        assert_eq!(get_pe_manifest_name(EXECUTABLE, &[3]), None);
+    }
+
+    fn identity(
+        name: &str,
+        version: &str,
+        processor_architecture: &str,
+        public_key_token: &str,
+        language: &str,
+    ) -> AssemblyIdentity {
+        let mut identity = AssemblyIdentity::new();
+        identity.name = name.to_string();
+        identity.version = version.to_string();
+        identity.processor_architecture = processor_architecture.to_string();
+        identity.public_key_token = public_key_token.to_string();
+        identity.language = language.to_string();
+
+        return identity;
+    }
+
+    #[test]
+    fn entities_are_decoded_once() {
+        assert_eq!(
+            decode_entities("&lt;a&gt; &quot;b&quot; &apos;c&apos; &amp;lt;"),
+            "<a> \"b\" 'c' &lt;"
+        );
+        assert_eq!(decode_entities("a & b"), "a & b");
+    }
+
+    #[test]
+    fn xml_elements_are_scanned_with_their_ancestors() {
+        let elements = scan_xml_elements(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- <ignored/> -->
+            <asm:assembly xmlns:asm="urn:schemas-microsoft-com:asm.v1" manifestVersion='1.0'>
+              <![CDATA[<ignored/>]]>
+              <asm:dependency>
+                <dependentAssembly>
+                  <assemblyIdentity name="a&amp;b" version = "1.0.0.0" />
+                </dependentAssembly>
+              </asm:dependency>
+              <file name="a.dll"/>
+            </asm:assembly>"#,
+        );
+
+        let names: Vec<&str> = elements.iter().map(|e| e.name.as_str()).collect();
+
+        assert_eq!(
+            names,
+            [
+                "assembly",
+                "dependency",
+                "dependentAssembly",
+                "assemblyIdentity",
+                "file"
+            ]
+        );
+
+        assert_eq!(elements[0].get_attribute("manifestVersion"), Some("1.0"));
+        assert_eq!(
+            elements[3].ancestors,
+            ["assembly", "dependency", "dependentAssembly"]
+        );
+        assert_eq!(elements[3].get_attribute("NAME"), Some("a&b"));
+        assert_eq!(elements[3].get_attribute("version"), Some("1.0.0.0"));
+        assert_eq!(elements[4].get_parent(), Some("assembly"));
+    }
+
+    #[test]
+    fn truncated_xml_is_scanned_as_far_as_possible() {
+        let elements = scan_xml_elements(r#"<assembly><file name="a.dll"/><file name="b"#);
+
+        assert_eq!(elements.len(), 2);
+        assert_eq!(elements[1].get_attribute("name"), Some("a.dll"));
+    }
+
+    #[test]
+    fn identities_match_their_references() {
+        let assembly = identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "amd64",
+            "1fc8b3b9a1e18e3b",
+            "none",
+        );
+
+        assert!(assembly.matches(&identity(
+            "microsoft.vc90.crt",
+            "9.0.21022.8",
+            "AMD64",
+            "1FC8B3B9A1E18E3B",
+            ""
+        )));
+
+        /* Any processor architecture or language */
+        assert!(assembly.matches(&identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "*",
+            "1fc8b3b9a1e18e3b",
+            "*"
+        )));
+
+        /* "neutral", "none" and an empty language are the same */
+        assert!(assembly.matches(&identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "amd64",
+            "1fc8b3b9a1e18e3b",
+            "neutral"
+        )));
+
+        /* Private assemblies are not signed */
+        assert!(assembly.matches(&identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "amd64",
+            "",
+            ""
+        )));
+
+        assert!(!assembly.matches(&identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.9",
+            "amd64",
+            "1fc8b3b9a1e18e3b",
+            ""
+        )));
+        assert!(!assembly.matches(&identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "x86",
+            "1fc8b3b9a1e18e3b",
+            ""
+        )));
+        assert!(!assembly.matches(&identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "amd64",
+            "0000000000000000",
+            ""
+        )));
+        assert!(!assembly.matches(&identity(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "amd64",
+            "1fc8b3b9a1e18e3b",
+            "en-us"
+        )));
+        assert!(
+            !identity("Microsoft.VC90.CRT", "9.0.21022.8", "amd64", "", "en-us").matches(
+                &identity("Microsoft.VC90.CRT", "9.0.21022.8", "amd64", "", "")
+            )
+        );
+    }
+
+    #[test]
+    fn binding_redirects_apply_to_their_version_range() {
+        let manifest = Manifest::parse(
+            r#"<configuration><runtime><assemblyBinding>
+                <dependentAssembly>
+                  <assemblyIdentity name="a" processorArchitecture="amd64"/>
+                  <bindingRedirect oldVersion="1.0.0.0-1.0.65535.65535" newVersion=" 1.0.2.0 "/>
+                </dependentAssembly>
+                <dependentAssembly>
+                  <assemblyIdentity name="b"/>
+                  <bindingRedirect oldVersion="2.0.0.0" newVersion="2.1.0.0"/>
+                  <bindingRedirect oldVersion="2.0.0-2.1.0.0" newVersion="3.0.0.0"/>
+                </dependentAssembly>
+            </assemblyBinding></runtime></configuration>"#,
+        );
+
+        /* Invalid versions are ignored */
+        assert_eq!(manifest.binding_redirects.len(), 2);
+
+        let a = &manifest.binding_redirects[0];
+
+        assert_eq!(a.old_version, ([1, 0, 0, 0], [1, 0, 65535, 65535]));
+        assert_eq!(a.new_version, "1.0.2.0");
+        assert!(a.applies_to(&identity("A", "1.0.0.0", "", "", "")));
+        assert!(a.applies_to(&identity("a", "1.0.65535.65535", "", "", "")));
+        assert!(!a.applies_to(&identity("a", "1.1.0.0", "", "", "")));
+        assert!(!a.applies_to(&identity("b", "1.0.0.0", "", "", "")));
+
+        let b = &manifest.binding_redirects[1];
+
+        assert_eq!(b.old_version, ([2, 0, 0, 0], [2, 0, 0, 0]));
+        assert!(!b.applies_to(&identity("b", "2.0.0.1", "", "", "")));
+
+        assert_eq!(
+            manifest
+                .redirect(&identity("b", "2.0.0.0", "x86", "", ""))
+                .unwrap(),
+            identity("b", "2.1.0.0", "x86", "", "")
+        );
+        assert!(
+            manifest
+                .redirect(&identity("c", "2.0.0.0", "", "", ""))
+                .is_none()
+        );
+    }
+}
@@ -1,13 +1,132 @@
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::cell::OnceCell;
 use std::collections::HashMap;
-use std::error::Error;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /*
  * https://learn.microsoft.com/en-us/windows/win32/debug/pe-format
  */
 
+/*
+ * Errors raised while parsing a PE. Offsets are file offsets
+ */
+
+#[derive(Debug)]
+pub enum PeError {
+    Io {
+        path: PathBuf,
+        source: io::Error,
+    },
+    UnsupportedExtension {
+        path: PathBuf,
+    },
+    Truncated {
+        offset: u64,
+    },
+    BadMagic {
+        offset: u64,
+        what: &'static str,
+        found: u32,
+    },
+    RvaOutOfRange {
+        rva: u32,
+        what: &'static str,
+    },
+    InvalidName {
+        offset: u64,
+    },
+    Malformed {
+        offset: u64,
+        reason: &'static str,
+    },
+}
+
+impl std::fmt::Display for PeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PeError::Io { path, source } => {
+                return write!(f, "Cannot read file \"{}\" ({})", path.display(), source);
+            }
+            PeError::UnsupportedExtension { path } => {
+                return write!(
+                    f,
+                    "File \"{}\" does not have a Portable Executable extension ({})",
+                    path.display(),
+                    PE_EXTENSIONS.join(" | ")
+                );
+            }
+            PeError::Truncated { offset } => {
+                return write!(f, "File is truncated, cannot read at offset {offset:#x}");
+            }
+            PeError::BadMagic {
+                offset,
+                what,
+                found,
+            } => {
+                return write!(f, "Invalid {what} ({found:#x}) at offset {offset:#x}");
+            }
+            PeError::RvaOutOfRange { rva, what } => {
+                return write!(f, "{what} RVA {rva:#x} does not map to any section");
+            }
+            PeError::InvalidName { offset } => {
+                return write!(f, "Invalid name found at offset {offset:#x}");
+            }
+            PeError::Malformed { offset, reason } => {
+                return write!(f, "{reason} (at offset {offset:#x})");
+            }
+        }
+    }
+}
+
+impl std::error::Error for PeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PeError::Io { source, .. } => return Some(source),
+            _ => return None,
+        }
+    }
+}
+
+/*
+ * Bounds-checked little-endian reads, reporting the offset at which the file is truncated
+ */
+
+trait PERead {
+    fn read_byte(&mut self) -> Result<u8, PeError>;
+    fn read_word(&mut self) -> Result<u16, PeError>;
+    fn read_dword(&mut self) -> Result<u32, PeError>;
+    fn read_qword(&mut self) -> Result<u64, PeError>;
+}
+
+impl<T: AsRef<[u8]>> PERead for io::Cursor<T> {
+    fn read_byte(&mut self) -> Result<u8, PeError> {
+        let offset = self.position();
+        return self.read_u8().map_err(|_| PeError::Truncated { offset });
+    }
+
+    fn read_word(&mut self) -> Result<u16, PeError> {
+        let offset = self.position();
+        return self
+            .read_u16::<LittleEndian>()
+            .map_err(|_| PeError::Truncated { offset });
+    }
+
+    fn read_dword(&mut self) -> Result<u32, PeError> {
+        let offset = self.position();
+        return self
+            .read_u32::<LittleEndian>()
+            .map_err(|_| PeError::Truncated { offset });
+    }
+
+    fn read_qword(&mut self) -> Result<u64, PeError> {
+        let offset = self.position();
+        return self
+            .read_u64::<LittleEndian>()
+            .map_err(|_| PeError::Truncated { offset });
+    }
+}
+
 /*
  * MS-DOS Header present in every PE file
  */
@@ -27,17 +146,21 @@ impl DOSHeader {
         return DOSHeader::default();
     }
 
-    fn from_parser(cursor: &mut io::Cursor<&Vec<u8>>) -> Result<DOSHeader, Box<dyn Error>> {
+    fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<DOSHeader, PeError> {
         let mut header: DOSHeader = DOSHeader::new();
-        header.magic = cursor.read_u16::<LittleEndian>()?;
+        header.magic = cursor.read_word()?;
 
         if header.magic != DOS_MAGIC {
-            return Err("Invalid DOS magic number".into());
+            return Err(PeError::BadMagic {
+                offset: 0,
+                what: "DOS magic number",
+                found: header.magic as u32,
+            });
         }
 
         cursor.set_position(0x3C);
 
-        header.lfanew = cursor.read_u32::<LittleEndian>()?;
+        header.lfanew = cursor.read_dword()?;
 
         return Ok(header);
     }
@@ -47,6 +170,158 @@ impl DOSHeader {
  * COFF Header
  */
 
+/* Machine types. ARM64EC and ARM64X only appear in object files, their images are AMD64 and ARM64 */
+pub const IMAGE_FILE_MACHINE_UNKNOWN: u16 = 0x0;
+pub const IMAGE_FILE_MACHINE_I386: u16 = 0x14c;
+pub const IMAGE_FILE_MACHINE_ARMNT: u16 = 0x1c4;
+pub const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+pub const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
+pub const IMAGE_FILE_MACHINE_ARM64EC: u16 = 0xa641;
+pub const IMAGE_FILE_MACHINE_ARM64X: u16 = 0xa64e;
+
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Machine {
+    #[default]
+    Unknown,
+    I386,
+    ArmNt,
+    Amd64,
+    Arm64,
+    Arm64Ec,
+    Arm64X,
+    Other(u16),
+}
+
+impl Machine {
+    pub fn from_u16(machine: u16) -> Machine {
+        match machine {
+            IMAGE_FILE_MACHINE_UNKNOWN => return Machine::Unknown,
+            IMAGE_FILE_MACHINE_I386 => return Machine::I386,
+            IMAGE_FILE_MACHINE_ARMNT => return Machine::ArmNt,
+            IMAGE_FILE_MACHINE_AMD64 => return Machine::Amd64,
+            IMAGE_FILE_MACHINE_ARM64 => return Machine::Arm64,
+            IMAGE_FILE_MACHINE_ARM64EC => return Machine::Arm64Ec,
+            IMAGE_FILE_MACHINE_ARM64X => return Machine::Arm64X,
+            other => return Machine::Other(other),
+        }
+    }
+
+    pub fn to_u16(self) -> u16 {
+        match self {
+            Machine::Unknown => return IMAGE_FILE_MACHINE_UNKNOWN,
+            Machine::I386 => return IMAGE_FILE_MACHINE_I386,
+            Machine::ArmNt => return IMAGE_FILE_MACHINE_ARMNT,
+            Machine::Amd64 => return IMAGE_FILE_MACHINE_AMD64,
+            Machine::Arm64 => return IMAGE_FILE_MACHINE_ARM64,
+            Machine::Arm64Ec => return IMAGE_FILE_MACHINE_ARM64EC,
+            Machine::Arm64X => return IMAGE_FILE_MACHINE_ARM64X,
+            Machine::Other(other) => return other,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Machine::I386 => return "x86",
+            Machine::ArmNt => return "ARM",
+            Machine::Amd64 => return "x64",
+            Machine::Arm64 => return "ARM64",
+            Machine::Arm64Ec => return "ARM64EC",
+            Machine::Arm64X => return "ARM64X",
+            Machine::Unknown | Machine::Other(_) => return "unknown",
+        }
+    }
+}
+
+impl std::fmt::Display for Machine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Machine::Other(other) => return write!(f, "{other:#06x}"),
+            _ => return write!(f, "{}", self.get_name()),
+        }
+    }
+}
+
+/*
+ * Characteristics of the image (COFF header)
+ */
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Characteristics(u16);
+
+impl Characteristics {
+    pub const RELOCS_STRIPPED: Characteristics = Characteristics(0x0001);
+    pub const EXECUTABLE_IMAGE: Characteristics = Characteristics(0x0002);
+    pub const LINE_NUMS_STRIPPED: Characteristics = Characteristics(0x0004);
+    pub const LOCAL_SYMS_STRIPPED: Characteristics = Characteristics(0x0008);
+    pub const AGGRESSIVE_WS_TRIM: Characteristics = Characteristics(0x0010);
+    pub const LARGE_ADDRESS_AWARE: Characteristics = Characteristics(0x0020);
+    pub const BYTES_REVERSED_LO: Characteristics = Characteristics(0x0080);
+    pub const MACHINE_32BIT: Characteristics = Characteristics(0x0100);
+    pub const DEBUG_STRIPPED: Characteristics = Characteristics(0x0200);
+    pub const REMOVABLE_RUN_FROM_SWAP: Characteristics = Characteristics(0x0400);
+    pub const NET_RUN_FROM_SWAP: Characteristics = Characteristics(0x0800);
+    pub const SYSTEM: Characteristics = Characteristics(0x1000);
+    pub const DLL: Characteristics = Characteristics(0x2000);
+    pub const UP_SYSTEM_ONLY: Characteristics = Characteristics(0x4000);
+    pub const BYTES_REVERSED_HI: Characteristics = Characteristics(0x8000);
+
+    const NAMES: &[(Characteristics, &'static str)] = &[
+        (Characteristics::RELOCS_STRIPPED, "RELOCS_STRIPPED"),
+        (Characteristics::EXECUTABLE_IMAGE, "EXECUTABLE_IMAGE"),
+        (Characteristics::LINE_NUMS_STRIPPED, "LINE_NUMS_STRIPPED"),
+        (Characteristics::LOCAL_SYMS_STRIPPED, "LOCAL_SYMS_STRIPPED"),
+        (Characteristics::AGGRESSIVE_WS_TRIM, "AGGRESSIVE_WS_TRIM"),
+        (Characteristics::LARGE_ADDRESS_AWARE, "LARGE_ADDRESS_AWARE"),
+        (Characteristics::BYTES_REVERSED_LO, "BYTES_REVERSED_LO"),
+        (Characteristics::MACHINE_32BIT, "32BIT_MACHINE"),
+        (Characteristics::DEBUG_STRIPPED, "DEBUG_STRIPPED"),
+        (
+            Characteristics::REMOVABLE_RUN_FROM_SWAP,
+            "REMOVABLE_RUN_FROM_SWAP",
+        ),
+        (Characteristics::NET_RUN_FROM_SWAP, "NET_RUN_FROM_SWAP"),
+        (Characteristics::SYSTEM, "SYSTEM"),
+        (Characteristics::DLL, "DLL"),
+        (Characteristics::UP_SYSTEM_ONLY, "UP_SYSTEM_ONLY"),
+        (Characteristics::BYTES_REVERSED_HI, "BYTES_REVERSED_HI"),
+    ];
+
+    pub fn from_bits(bits: u16) -> Characteristics {
+        return Characteristics(bits);
+    }
+
+    pub fn bits(&self) -> u16 {
+        return self.0;
+    }
+
+    pub fn contains(&self, other: Characteristics) -> bool {
+        return self.0 & other.0 == other.0;
+    }
+
+    pub fn get_names(&self) -> Vec<&'static str> {
+        return Characteristics::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+    }
+}
+
+impl std::ops::BitOr for Characteristics {
+    type Output = Characteristics;
+
+    fn bitor(self, other: Characteristics) -> Characteristics {
+        return Characteristics(self.0 | other.0);
+    }
+}
+
+impl std::ops::BitAnd for Characteristics {
+    type Output = Characteristics;
+
+    fn bitand(self, other: Characteristics) -> Characteristics {
+        return Characteristics(self.0 & other.0);
+    }
+}
+
 #[derive(Default, Clone, Debug)]
 #[repr(C)]
 pub struct COFFHeader {
@@ -60,16 +335,16 @@ pub struct COFFHeader {
 }
 
 impl COFFHeader {
-    fn from_parser(cursor: &mut io::Cursor<&Vec<u8>>) -> Result<COFFHeader, Box<dyn Error>> {
+    fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<COFFHeader, PeError> {
         let mut header: COFFHeader = COFFHeader::default();
 
-        header.machine = cursor.read_u16::<LittleEndian>()?;
-        header.number_of_sections = cursor.read_u16::<LittleEndian>()?;
-        header.time_date_stamp = cursor.read_u32::<LittleEndian>()?;
-        header.pointer_to_symbol_table = cursor.read_u32::<LittleEndian>()?;
-        header.number_of_symbols = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_optional_header = cursor.read_u16::<LittleEndian>()?;
-        header.characteristics = cursor.read_u16::<LittleEndian>()?;
+        header.machine = cursor.read_word()?;
+        header.number_of_sections = cursor.read_word()?;
+        header.time_date_stamp = cursor.read_dword()?;
+        header.pointer_to_symbol_table = cursor.read_dword()?;
+        header.number_of_symbols = cursor.read_dword()?;
+        header.size_of_optional_header = cursor.read_word()?;
+        header.characteristics = cursor.read_word()?;
 
         return Ok(header);
     }
@@ -85,12 +360,17 @@ pub struct NTHeader {
 }
 
 impl NTHeader {
-    fn from_parser(cursor: &mut io::Cursor<&Vec<u8>>) -> Result<NTHeader, Box<dyn Error>> {
+    fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<NTHeader, PeError> {
         let mut header: NTHeader = NTHeader::default();
-        header.signature = cursor.read_u32::<LittleEndian>()?;
+        let offset = cursor.position();
+        header.signature = cursor.read_dword()?;
 
         if header.signature != NT_PE_SIGNATURE {
-            return Err("Invalid PE signature in NT Header".into());
+            return Err(PeError::BadMagic {
+                offset,
+                what: "PE signature in NT Header",
+                found: header.signature,
+            });
         }
 
         header.coff_header = COFFHeader::from_parser(cursor)?;
@@ -115,13 +395,11 @@ impl ImageDataDirectory {
         return ImageDataDirectory::default();
     }
 
-    pub fn from_parser(
-        cursor: &mut io::Cursor<&Vec<u8>>,
-    ) -> Result<ImageDataDirectory, Box<dyn std::error::Error>> {
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<ImageDataDirectory, PeError> {
         let mut idd = ImageDataDirectory::new();
 
-        idd.virtual_address = cursor.read_u32::<LittleEndian>()?;
-        idd.size = cursor.read_u32::<LittleEndian>()?;
+        idd.virtual_address = cursor.read_dword()?;
+        idd.size = cursor.read_dword()?;
 
         return Ok(idd);
     }
@@ -131,6 +409,149 @@ impl ImageDataDirectory {
  * Optional Header for 32/32+ images
  */
 
+/*
+ * Subsystem required to run the image (optional header)
+ */
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subsystem {
+    #[default]
+    Unknown,
+    Native,
+    WindowsGui,
+    WindowsCui,
+    Os2Cui,
+    PosixCui,
+    NativeWindows,
+    WindowsCeGui,
+    EfiApplication,
+    EfiBootServiceDriver,
+    EfiRuntimeDriver,
+    EfiRom,
+    Xbox,
+    WindowsBootApplication,
+    Other(u16),
+}
+
+impl Subsystem {
+    pub fn from_u16(subsystem: u16) -> Subsystem {
+        match subsystem {
+            0 => return Subsystem::Unknown,
+            1 => return Subsystem::Native,
+            2 => return Subsystem::WindowsGui,
+            3 => return Subsystem::WindowsCui,
+            5 => return Subsystem::Os2Cui,
+            7 => return Subsystem::PosixCui,
+            8 => return Subsystem::NativeWindows,
+            9 => return Subsystem::WindowsCeGui,
+            10 => return Subsystem::EfiApplication,
+            11 => return Subsystem::EfiBootServiceDriver,
+            12 => return Subsystem::EfiRuntimeDriver,
+            13 => return Subsystem::EfiRom,
+            14 => return Subsystem::Xbox,
+            16 => return Subsystem::WindowsBootApplication,
+            other => return Subsystem::Other(other),
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Subsystem::Unknown | Subsystem::Other(_) => return "unknown",
+            Subsystem::Native => return "native",
+            Subsystem::WindowsGui => return "windows_gui",
+            Subsystem::WindowsCui => return "windows_cui",
+            Subsystem::Os2Cui => return "os2_cui",
+            Subsystem::PosixCui => return "posix_cui",
+            Subsystem::NativeWindows => return "native_windows",
+            Subsystem::WindowsCeGui => return "windows_ce_gui",
+            Subsystem::EfiApplication => return "efi_application",
+            Subsystem::EfiBootServiceDriver => return "efi_boot_service_driver",
+            Subsystem::EfiRuntimeDriver => return "efi_runtime_driver",
+            Subsystem::EfiRom => return "efi_rom",
+            Subsystem::Xbox => return "xbox",
+            Subsystem::WindowsBootApplication => return "windows_boot_application",
+        }
+    }
+}
+
+impl std::fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}", self.get_name());
+    }
+}
+
+/*
+ * DLL characteristics (optional header), mostly the security mitigations the image opts in
+ */
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DllCharacteristics(u16);
+
+impl DllCharacteristics {
+    pub const HIGH_ENTROPY_VA: DllCharacteristics = DllCharacteristics(0x0020);
+    pub const DYNAMIC_BASE: DllCharacteristics = DllCharacteristics(0x0040);
+    pub const FORCE_INTEGRITY: DllCharacteristics = DllCharacteristics(0x0080);
+    pub const NX_COMPAT: DllCharacteristics = DllCharacteristics(0x0100);
+    pub const NO_ISOLATION: DllCharacteristics = DllCharacteristics(0x0200);
+    pub const NO_SEH: DllCharacteristics = DllCharacteristics(0x0400);
+    pub const NO_BIND: DllCharacteristics = DllCharacteristics(0x0800);
+    pub const APPCONTAINER: DllCharacteristics = DllCharacteristics(0x1000);
+    pub const WDM_DRIVER: DllCharacteristics = DllCharacteristics(0x2000);
+    pub const GUARD_CF: DllCharacteristics = DllCharacteristics(0x4000);
+    pub const TERMINAL_SERVER_AWARE: DllCharacteristics = DllCharacteristics(0x8000);
+
+    const NAMES: &[(DllCharacteristics, &'static str)] = &[
+        (DllCharacteristics::HIGH_ENTROPY_VA, "HIGH_ENTROPY_VA"),
+        (DllCharacteristics::DYNAMIC_BASE, "DYNAMIC_BASE"),
+        (DllCharacteristics::FORCE_INTEGRITY, "FORCE_INTEGRITY"),
+        (DllCharacteristics::NX_COMPAT, "NX_COMPAT"),
+        (DllCharacteristics::NO_ISOLATION, "NO_ISOLATION"),
+        (DllCharacteristics::NO_SEH, "NO_SEH"),
+        (DllCharacteristics::NO_BIND, "NO_BIND"),
+        (DllCharacteristics::APPCONTAINER, "APPCONTAINER"),
+        (DllCharacteristics::WDM_DRIVER, "WDM_DRIVER"),
+        (DllCharacteristics::GUARD_CF, "GUARD_CF"),
+        (
+            DllCharacteristics::TERMINAL_SERVER_AWARE,
+            "TERMINAL_SERVER_AWARE",
+        ),
+    ];
+
+    pub fn from_bits(bits: u16) -> DllCharacteristics {
+        return DllCharacteristics(bits);
+    }
+
+    pub fn bits(&self) -> u16 {
+        return self.0;
+    }
+
+    pub fn contains(&self, other: DllCharacteristics) -> bool {
+        return self.0 & other.0 == other.0;
+    }
+
+    pub fn get_names(&self) -> Vec<&'static str> {
+        return DllCharacteristics::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect();
+    }
+}
+
+impl std::ops::BitOr for DllCharacteristics {
+    type Output = DllCharacteristics;
+
+    fn bitor(self, other: DllCharacteristics) -> DllCharacteristics {
+        return DllCharacteristics(self.0 | other.0);
+    }
+}
+
+impl std::ops::BitAnd for DllCharacteristics {
+    type Output = DllCharacteristics;
+
+    fn bitand(self, other: DllCharacteristics) -> DllCharacteristics {
+        return DllCharacteristics(self.0 & other.0);
+    }
+}
+
 /* Magic number for 32 bits PE */
 const PE_FORMAT_32_MAGIC: u16 = 0x10b;
 
@@ -198,39 +619,39 @@ impl OptionalHeader32 {
         return OptionalHeader32::default();
     }
 
-    fn from_parser(cursor: &mut io::Cursor<&Vec<u8>>) -> Result<OptionalHeader32, Box<dyn Error>> {
+    fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<OptionalHeader32, PeError> {
         let mut header: OptionalHeader32 = OptionalHeader32::new();
 
-        header.magic = cursor.read_u16::<LittleEndian>()?;
-        header.major_linker_version = cursor.read_u8()?;
-        header.minor_linker_version = cursor.read_u8()?;
-        header.size_of_code = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_initialized_data = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_uninitialized_data = cursor.read_u32::<LittleEndian>()?;
-        header.address_of_entry_point = cursor.read_u32::<LittleEndian>()?;
-        header.base_of_code = cursor.read_u32::<LittleEndian>()?;
-        header.base_of_data = cursor.read_u32::<LittleEndian>()?;
-        header.image_base = cursor.read_u32::<LittleEndian>()?;
-        header.section_alignment = cursor.read_u32::<LittleEndian>()?;
-        header.file_alignement = cursor.read_u32::<LittleEndian>()?;
-        header.major_operating_system_version = cursor.read_u16::<LittleEndian>()?;
-        header.minor_operating_system_version = cursor.read_u16::<LittleEndian>()?;
-        header.major_image_version = cursor.read_u16::<LittleEndian>()?;
-        header.minor_image_version = cursor.read_u16::<LittleEndian>()?;
-        header.major_subsystem_version = cursor.read_u16::<LittleEndian>()?;
-        header.minor_subsystem_version = cursor.read_u16::<LittleEndian>()?;
-        header.win32_version_value = cursor.read_u32::<LittleEndian>()?; /* reserved field */
-        header.size_of_image = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_headers = cursor.read_u32::<LittleEndian>()?;
-        header.checksum = cursor.read_u32::<LittleEndian>()?;
-        header.subsystem = cursor.read_u16::<LittleEndian>()?;
-        header.dll_characteristics = cursor.read_u16::<LittleEndian>()?;
-        header.size_of_stack_reserve = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_stack_commit = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_heap_reserve = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_heap_commit = cursor.read_u32::<LittleEndian>()?;
-        header.loader_flags = cursor.read_u32::<LittleEndian>()?; /* reserved_field */
-        header.number_of_rva_and_sizes = cursor.read_u32::<LittleEndian>()?;
+        header.magic = cursor.read_word()?;
+        header.major_linker_version = cursor.read_byte()?;
+        header.minor_linker_version = cursor.read_byte()?;
+        header.size_of_code = cursor.read_dword()?;
+        header.size_of_initialized_data = cursor.read_dword()?;
+        header.size_of_uninitialized_data = cursor.read_dword()?;
+        header.address_of_entry_point = cursor.read_dword()?;
+        header.base_of_code = cursor.read_dword()?;
+        header.base_of_data = cursor.read_dword()?;
+        header.image_base = cursor.read_dword()?;
+        header.section_alignment = cursor.read_dword()?;
+        header.file_alignement = cursor.read_dword()?;
+        header.major_operating_system_version = cursor.read_word()?;
+        header.minor_operating_system_version = cursor.read_word()?;
+        header.major_image_version = cursor.read_word()?;
+        header.minor_image_version = cursor.read_word()?;
+        header.major_subsystem_version = cursor.read_word()?;
+        header.minor_subsystem_version = cursor.read_word()?;
+        header.win32_version_value = cursor.read_dword()?; /* reserved field */
+        header.size_of_image = cursor.read_dword()?;
+        header.size_of_headers = cursor.read_dword()?;
+        header.checksum = cursor.read_dword()?;
+        header.subsystem = cursor.read_word()?;
+        header.dll_characteristics = cursor.read_word()?;
+        header.size_of_stack_reserve = cursor.read_dword()?;
+        header.size_of_stack_commit = cursor.read_dword()?;
+        header.size_of_heap_reserve = cursor.read_dword()?;
+        header.size_of_heap_commit = cursor.read_dword()?;
+        header.loader_flags = cursor.read_dword()?; /* reserved_field */
+        header.number_of_rva_and_sizes = cursor.read_dword()?;
         header.export_table = ImageDataDirectory::from_parser(cursor)?;
         header.import_table = ImageDataDirectory::from_parser(cursor)?;
         header.resource_table = ImageDataDirectory::from_parser(cursor)?;
@@ -312,38 +733,38 @@ impl OptionalHeader64 {
         return OptionalHeader64::default();
     }
 
-    fn from_parser(cursor: &mut io::Cursor<&Vec<u8>>) -> Result<OptionalHeader64, Box<dyn Error>> {
+    fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<OptionalHeader64, PeError> {
         let mut header: OptionalHeader64 = OptionalHeader64::new();
 
-        header.magic = cursor.read_u16::<LittleEndian>()?;
-        header.major_linker_version = cursor.read_u8()?;
-        header.minor_linker_version = cursor.read_u8()?;
-        header.size_of_code = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_initialized_data = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_uninitialized_data = cursor.read_u32::<LittleEndian>()?;
-        header.address_of_entry_point = cursor.read_u32::<LittleEndian>()?;
-        header.base_of_code = cursor.read_u32::<LittleEndian>()?;
-        header.image_base = cursor.read_u64::<LittleEndian>()?;
-        header.section_alignment = cursor.read_u32::<LittleEndian>()?;
-        header.file_alignement = cursor.read_u32::<LittleEndian>()?;
-        header.major_operating_system_version = cursor.read_u16::<LittleEndian>()?;
-        header.minor_operating_system_version = cursor.read_u16::<LittleEndian>()?;
-        header.major_image_version = cursor.read_u16::<LittleEndian>()?;
-        header.minor_image_version = cursor.read_u16::<LittleEndian>()?;
-        header.major_subsystem_version = cursor.read_u16::<LittleEndian>()?;
-        header.minor_subsystem_version = cursor.read_u16::<LittleEndian>()?;
-        header.win32_version_value = cursor.read_u32::<LittleEndian>()?; /* reserved field */
-        header.size_of_image = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_headers = cursor.read_u32::<LittleEndian>()?;
-        header.checksum = cursor.read_u32::<LittleEndian>()?;
-        header.subsystem = cursor.read_u16::<LittleEndian>()?;
-        header.dll_characteristics = cursor.read_u16::<LittleEndian>()?;
-        header.size_of_stack_reserve = cursor.read_u64::<LittleEndian>()?;
-        header.size_of_stack_commit = cursor.read_u64::<LittleEndian>()?;
-        header.size_of_heap_reserve = cursor.read_u64::<LittleEndian>()?;
-        header.size_of_heap_commit = cursor.read_u64::<LittleEndian>()?;
-        header.loader_flags = cursor.read_u32::<LittleEndian>()?; /* reserved_field */
-        header.number_of_rva_and_sizes = cursor.read_u32::<LittleEndian>()?;
+        header.magic = cursor.read_word()?;
+        header.major_linker_version = cursor.read_byte()?;
+        header.minor_linker_version = cursor.read_byte()?;
+        header.size_of_code = cursor.read_dword()?;
+        header.size_of_initialized_data = cursor.read_dword()?;
+        header.size_of_uninitialized_data = cursor.read_dword()?;
+        header.address_of_entry_point = cursor.read_dword()?;
+        header.base_of_code = cursor.read_dword()?;
+        header.image_base = cursor.read_qword()?;
+        header.section_alignment = cursor.read_dword()?;
+        header.file_alignement = cursor.read_dword()?;
+        header.major_operating_system_version = cursor.read_word()?;
+        header.minor_operating_system_version = cursor.read_word()?;
+        header.major_image_version = cursor.read_word()?;
+        header.minor_image_version = cursor.read_word()?;
+        header.major_subsystem_version = cursor.read_word()?;
+        header.minor_subsystem_version = cursor.read_word()?;
+        header.win32_version_value = cursor.read_dword()?; /* reserved field */
+        header.size_of_image = cursor.read_dword()?;
+        header.size_of_headers = cursor.read_dword()?;
+        header.checksum = cursor.read_dword()?;
+        header.subsystem = cursor.read_word()?;
+        header.dll_characteristics = cursor.read_word()?;
+        header.size_of_stack_reserve = cursor.read_qword()?;
+        header.size_of_stack_commit = cursor.read_qword()?;
+        header.size_of_heap_reserve = cursor.read_qword()?;
+        header.size_of_heap_commit = cursor.read_qword()?;
+        header.loader_flags = cursor.read_dword()?; /* reserved_field */
+        header.number_of_rva_and_sizes = cursor.read_dword()?;
         header.export_table = ImageDataDirectory::from_parser(cursor)?;
         header.import_table = ImageDataDirectory::from_parser(cursor)?;
         header.resource_table = ImageDataDirectory::from_parser(cursor)?;
@@ -390,53 +811,118 @@ impl SectionHeader {
     }
 
     fn from_parser(
-        cursor: &mut io::Cursor<&Vec<u8>>,
-    ) -> Result<SectionHeader, Box<dyn std::error::Error>> {
+        cursor: &mut io::Cursor<&[u8]>,
+        string_table_offset: Option<u64>,
+    ) -> Result<SectionHeader, PeError> {
         let mut header = SectionHeader::new();
 
-        let first_name_byte = cursor.read_u8()?;
+        let first_name_byte = cursor.read_byte()?;
 
-        if first_name_byte == 0x2F as u8 {
+        if first_name_byte == 0x2F {
             // "/"
-            todo!("Need to implement section header name finding in string table");
-        } else if first_name_byte == 0x0 as u8 {
+            // Long names are stored in the COFF string table, the section name being the offset
+            // of the name in the string table
+            let mut offset_buffer: Vec<u8> = Vec::new();
+
+            for _ in 0..7 {
+                let c = cursor.read_byte()?;
+
+                if c == b'\0' {
+                    continue;
+                }
+
+                offset_buffer.push(c);
+            }
+
+            let name_position = cursor.position() - 8;
+
+            let string_table_offset = string_table_offset.ok_or(PeError::Malformed {
+                offset: name_position,
+                reason: "Section name refers to the string table but there is no symbol table",
+            })?;
+
+            let name_offset =
+                parse_string_table_offset(&offset_buffer).ok_or(PeError::InvalidName {
+                    offset: name_position,
+                })?;
+
+            let position = cursor.position();
+
+            cursor.set_position(string_table_offset + name_offset);
+            header.name = read_null_terminated_string(cursor)?;
+            cursor.set_position(position);
+        } else if first_name_byte == 0x0 {
             // "\0"
             header.name = "empty".to_string();
             cursor.set_position(cursor.position() + 39);
 
             return Ok(header);
         } else {
+            let name_position = cursor.position() - 1;
             let mut name_buffer: Vec<u8> = Vec::new();
 
             name_buffer.push(first_name_byte);
 
             for _ in 0..7 {
-                let c = cursor.read_u8()?;
+                let c = cursor.read_byte()?;
 
-                if c == '\0' as u8 {
+                if c == b'\0' {
                     continue;
                 }
 
                 name_buffer.push(c);
             }
 
-            header.name = String::from_utf8(name_buffer).expect("Invalid section name found in PE");
+            header.name = String::from_utf8(name_buffer).map_err(|_| PeError::InvalidName {
+                offset: name_position,
+            })?;
         }
 
-        header.virtual_size = cursor.read_u32::<LittleEndian>()?;
-        header.virtual_address = cursor.read_u32::<LittleEndian>()?;
-        header.size_of_raw_data = cursor.read_u32::<LittleEndian>()?;
-        header.ptr_to_raw_data = cursor.read_u32::<LittleEndian>()?;
-        header.pointer_to_relocations = cursor.read_u32::<LittleEndian>()?;
-        header.pointer_to_line_numbers = cursor.read_u32::<LittleEndian>()?;
-        header.number_of_relocations = cursor.read_u16::<LittleEndian>()?;
-        header.number_of_line_numbers = cursor.read_u16::<LittleEndian>()?;
-        header.characteristics = cursor.read_u32::<LittleEndian>()?;
+        header.virtual_size = cursor.read_dword()?;
+        header.virtual_address = cursor.read_dword()?;
+        header.size_of_raw_data = cursor.read_dword()?;
+        header.ptr_to_raw_data = cursor.read_dword()?;
+        header.pointer_to_relocations = cursor.read_dword()?;
+        header.pointer_to_line_numbers = cursor.read_dword()?;
+        header.number_of_relocations = cursor.read_word()?;
+        header.number_of_line_numbers = cursor.read_word()?;
+        header.characteristics = cursor.read_dword()?;
 
         return Ok(header);
     }
 }
 
+/*
+ * Offset in the string table of a long section name, following the "/": decimal ("/123"), or
+ * base64 after a second "/" ("//AAAAAB") when it does not fit in 7 digits
+ */
+fn parse_string_table_offset(offset: &[u8]) -> Option<u64> {
+    let Some(base64_offset) = offset.strip_prefix(b"/") else {
+        return std::str::from_utf8(offset).ok()?.trim().parse::<u64>().ok();
+    };
+
+    if base64_offset.is_empty() {
+        return None;
+    }
+
+    let mut value: u64 = 0;
+
+    for c in base64_offset {
+        let digit = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+
+        value = value * 64 + digit as u64;
+    }
+
+    return Some(value);
+}
+
 /*
 * Typical segment names:
 * .text: Code
@@ -469,7 +955,7 @@ impl std::fmt::Debug for Section {
 
 impl Section {
     pub fn new(header: SectionHeader) -> Section {
-        return Section { header: header };
+        return Section { header };
     }
 }
 
@@ -492,16 +978,14 @@ impl ImageImportDescriptor {
         return ImageImportDescriptor::default();
     }
 
-    pub fn from_parser(
-        cursor: &mut io::Cursor<&Vec<u8>>,
-    ) -> Result<ImageImportDescriptor, Box<dyn std::error::Error>> {
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<ImageImportDescriptor, PeError> {
         let mut descriptor = ImageImportDescriptor::new();
 
-        descriptor.import_lookup_table_rva = cursor.read_u32::<LittleEndian>()?;
-        descriptor.time_date_stamp = cursor.read_u32::<LittleEndian>()?;
-        descriptor.forwarder_chain = cursor.read_u32::<LittleEndian>()?;
-        descriptor.name_rva = cursor.read_u32::<LittleEndian>()?;
-        descriptor.import_address_table_rva = cursor.read_u32::<LittleEndian>()?;
+        descriptor.import_lookup_table_rva = cursor.read_dword()?;
+        descriptor.time_date_stamp = cursor.read_dword()?;
+        descriptor.forwarder_chain = cursor.read_dword()?;
+        descriptor.name_rva = cursor.read_dword()?;
+        descriptor.import_address_table_rva = cursor.read_dword()?;
 
         return Ok(descriptor);
     }
@@ -513,6 +997,71 @@ impl ImageImportDescriptor {
             && self.name_rva == 0
             && self.import_address_table_rva == 0;
     }
+
+    /*
+     * Some linkers leave the Import Lookup Table RVA empty, in which case the loader falls back
+     * on the Import Address Table, which holds the same entries on disk
+     */
+    pub fn get_lookup_table_rva(&self) -> u32 {
+        if self.import_lookup_table_rva != 0 {
+            return self.import_lookup_table_rva;
+        }
+
+        return self.import_address_table_rva;
+    }
+}
+
+/*
+ * Image Delay Load Descriptor (struct found in the Delay-Load Directory Table)
+ */
+
+/* When set, the descriptor holds RVAs. Otherwise (Visual C++ 6 images), it holds VAs */
+const DELAY_LOAD_ATTRIBUTE_RVA_BASED: u32 = 0x1;
+
+const IMAGE_DELAY_LOAD_DESCRIPTOR_SIZE: u32 = 32;
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct ImageDelayLoadDescriptor {
+    attributes: u32,
+    name_rva: u32,
+    module_handle_rva: u32,
+    import_address_table_rva: u32,
+    import_name_table_rva: u32,
+    bound_import_address_table_rva: u32,
+    unload_information_table_rva: u32,
+    time_date_stamp: u32,
+}
+
+impl ImageDelayLoadDescriptor {
+    pub fn new() -> ImageDelayLoadDescriptor {
+        return ImageDelayLoadDescriptor::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut io::Cursor<&[u8]>,
+    ) -> Result<ImageDelayLoadDescriptor, PeError> {
+        let mut descriptor = ImageDelayLoadDescriptor::new();
+
+        descriptor.attributes = cursor.read_dword()?;
+        descriptor.name_rva = cursor.read_dword()?;
+        descriptor.module_handle_rva = cursor.read_dword()?;
+        descriptor.import_address_table_rva = cursor.read_dword()?;
+        descriptor.import_name_table_rva = cursor.read_dword()?;
+        descriptor.bound_import_address_table_rva = cursor.read_dword()?;
+        descriptor.unload_information_table_rva = cursor.read_dword()?;
+        descriptor.time_date_stamp = cursor.read_dword()?;
+
+        return Ok(descriptor);
+    }
+
+    pub fn is_zeroed_out(&self) -> bool {
+        return self.name_rva == 0 && self.import_address_table_rva == 0;
+    }
+
+    pub fn is_rva_based(&self) -> bool {
+        return (self.attributes & DELAY_LOAD_ATTRIBUTE_RVA_BASED) != 0;
+    }
 }
 
 #[derive(Default, Clone, Debug)]
@@ -529,33 +1078,37 @@ impl ImportLookupEntry {
     }
 
     pub fn from_parser(
-        cursor: &mut io::Cursor<&Vec<u8>>,
+        cursor: &mut io::Cursor<&[u8]>,
         is_32_bits: bool,
-    ) -> Result<ImportLookupEntry, Box<dyn std::error::Error>> {
+    ) -> Result<ImportLookupEntry, PeError> {
         let mut entry = ImportLookupEntry::new();
 
         if is_32_bits {
-            let data = cursor.read_u32::<LittleEndian>()?;
+            let data = cursor.read_dword()?;
             entry.by_ordinal = (data & 0x80000000) > 0;
 
             if entry.by_ordinal {
                 entry.ordinal_number = (data & 0xFFFF) as u16;
             } else {
-                entry.hint_name_table_rva = (data & 0x7FFFFFF) as u32;
+                entry.hint_name_table_rva = data & 0x7FFFFFFF;
             }
         } else {
-            let data = cursor.read_u64::<LittleEndian>()?;
+            let data = cursor.read_qword()?;
             entry.by_ordinal = (data & 0x8000000000000000) > 0;
 
             if entry.by_ordinal {
                 entry.ordinal_number = (data & 0xFFFF) as u16;
             } else {
-                entry.hint_name_table_rva = (data & 0x7FFFFFF) as u32;
+                entry.hint_name_table_rva = (data & 0x7FFFFFFF) as u32;
             }
         }
 
         return Ok(entry);
     }
+
+    pub fn is_zeroed_out(&self) -> bool {
+        return !self.by_ordinal && self.ordinal_number == 0 && self.hint_name_table_rva == 0;
+    }
 }
 
 #[derive(Default, Clone, Debug)]
@@ -571,17 +1124,16 @@ impl HintNameEntry {
         return HintNameEntry::default();
     }
 
-    pub fn from_parser(
-        cursor: &mut io::Cursor<&Vec<u8>>,
-    ) -> Result<HintNameEntry, Box<dyn std::error::Error>> {
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<HintNameEntry, PeError> {
         let mut entry = HintNameEntry::new();
 
-        entry.hint = cursor.read_u16::<LittleEndian>()?;
+        entry.hint = cursor.read_word()?;
 
+        let name_position = cursor.position();
         let mut name_buffer: Vec<u8> = Vec::new();
 
         loop {
-            let c = cursor.read_u8()?;
+            let c = cursor.read_byte()?;
 
             if c == 0x0 {
                 break;
@@ -590,281 +1142,2950 @@ impl HintNameEntry {
             name_buffer.push(c);
         }
 
-        if (name_buffer.len() % 2) != 0 {
-            cursor.read_u8()?;
+        if !name_buffer.len().is_multiple_of(2) {
+            cursor.read_byte()?;
             entry.pad = true;
         } else {
             entry.pad = false;
         }
 
-        entry.name = String::from_utf8(name_buffer).expect("Invalid name found in Hint/Name Table");
+        entry.name = String::from_utf8(name_buffer).map_err(|_| PeError::InvalidName {
+            offset: name_position,
+        })?;
 
         return Ok(entry);
     }
 }
 
 /*
- * PE Header
+ * Imported symbol, resolved from the Import Lookup Table and the Hint/Name Table
  */
+#[derive(Clone, Debug)]
+pub enum ImportedSymbol {
+    ByName { hint: u16, name: String },
+    ByOrdinal(u16),
+}
 
-#[allow(dead_code)]
-#[derive(Default, Clone, Debug)]
-pub struct PE32Header {
-    dos: DOSHeader,
-    nt: NTHeader,
-    optional: OptionalHeader32,
+impl std::fmt::Display for ImportedSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportedSymbol::ByName { name, .. } => return write!(f, "{name}"),
+            ImportedSymbol::ByOrdinal(ordinal) => return write!(f, "#{ordinal}"),
+        }
+    }
 }
 
-#[allow(dead_code)]
-#[derive(Default, Clone, Debug)]
-pub struct PE64Header {
-    dos: DOSHeader,
-    nt: NTHeader,
-    optional: OptionalHeader64,
+impl ImportedSymbol {
+    /*
+     * Builds the symbol targeted by a forwarder string ("NTDLL.RtlAllocateHeap" or "NTDLL.#12"),
+     * returning the name of the module it is forwarded to alongside
+     */
+    pub fn from_forwarder(forwarder: &str) -> Option<(String, ImportedSymbol)> {
+        let (module, function) = forwarder.rsplit_once('.')?;
+
+        let symbol = match function.strip_prefix('#') {
+            Some(ordinal) => ImportedSymbol::ByOrdinal(ordinal.parse::<u16>().ok()?),
+            None => ImportedSymbol::ByName {
+                hint: 0,
+                name: function.to_string(),
+            },
+        };
+
+        return Some((module.to_string(), symbol));
+    }
+
+    fn from_lookup_entry(
+        entry: &ImportLookupEntry,
+        pe: &PE,
+        cursor: &mut io::Cursor<&[u8]>,
+    ) -> Result<ImportedSymbol, PeError> {
+        if entry.by_ordinal {
+            return Ok(ImportedSymbol::ByOrdinal(entry.ordinal_number));
+        }
+
+        cursor.set_position(
+            pe.convert_rva_to_file_offset(entry.hint_name_table_rva)
+                .ok_or(PeError::RvaOutOfRange {
+                    rva: entry.hint_name_table_rva,
+                    what: "Hint/Name Table",
+                })?,
+        );
+
+        let hint_name = HintNameEntry::from_parser(cursor)?;
+
+        return Ok(ImportedSymbol::ByName {
+            hint: hint_name.hint,
+            name: hint_name.name,
+        });
+    }
 }
 
-#[derive(Clone, Debug)]
-pub enum PEHeader {
-    PE32(PE32Header),
-    PE64(PE64Header),
+/*
+ * Image Export Directory (struct found at the start of the Export Table (EDT))
+ */
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct ImageExportDirectory {
+    characteristics: u32, /* reserved field */
+    time_date_stamp: u32,
+    major_version: u16,
+    minor_version: u16,
+    name_rva: u32,
+    ordinal_base: u32,
+    number_of_functions: u32, /* Export Address Table entries */
+    number_of_names: u32,     /* Name Pointer Table and Ordinal Table entries */
+    address_of_functions: u32,
+    address_of_names: u32,
+    address_of_name_ordinals: u32,
 }
 
-impl Default for PEHeader {
-    fn default() -> PEHeader {
-        return PEHeader::PE32(PE32Header::default());
+impl ImageExportDirectory {
+    pub fn new() -> ImageExportDirectory {
+        return ImageExportDirectory::default();
+    }
+
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<ImageExportDirectory, PeError> {
+        let mut directory = ImageExportDirectory::new();
+
+        directory.characteristics = cursor.read_dword()?;
+        directory.time_date_stamp = cursor.read_dword()?;
+        directory.major_version = cursor.read_word()?;
+        directory.minor_version = cursor.read_word()?;
+        directory.name_rva = cursor.read_dword()?;
+        directory.ordinal_base = cursor.read_dword()?;
+        directory.number_of_functions = cursor.read_dword()?;
+        directory.number_of_names = cursor.read_dword()?;
+        directory.address_of_functions = cursor.read_dword()?;
+        directory.address_of_names = cursor.read_dword()?;
+        directory.address_of_name_ordinals = cursor.read_dword()?;
+
+        return Ok(directory);
     }
 }
 
 /*
- * PE
+ * Exported symbol. When the RVA points inside the export section, the symbol is a forwarder and
+ * the RVA points to a string such as "NTDLL.RtlAllocateHeap" or "NTDLL.#12"
  */
+#[derive(Default, Clone, Debug)]
+pub struct ExportedSymbol {
+    pub name: Option<String>,
+    pub ordinal: u16,
+    pub rva: u32,
+    pub forwarder: Option<String>,
+}
 
-pub enum PEArchitecture {
-    PE32,
-    PE64,
+impl ExportedSymbol {
+    pub fn is_forwarder(&self) -> bool {
+        return self.forwarder.is_some();
+    }
 }
 
-#[derive(Default, Debug)]
-pub struct PE {
-    pub header: PEHeader,
-    pub sections: HashMap<String, Section>,
-    pub import_descriptors: Vec<ImageImportDescriptor>,
-    pub dll_names: Vec<String>,
-    pub data: Vec<u8>,
+/*
+ * Exported symbols, indexed by name and by ordinal as they are looked up once per imported symbol
+ */
+#[derive(Default, Clone, Debug)]
+pub struct ExportTable {
+    pub dll_name: String,
+    pub ordinal_base: u32,
+    symbols: Vec<ExportedSymbol>,
+    names: HashMap<String, usize>,
+    ordinals: HashMap<u16, usize>,
 }
 
-impl PE {
-    pub fn new() -> PE {
-        return PE::default();
+impl ExportTable {
+    pub fn new(dll_name: String, ordinal_base: u32) -> ExportTable {
+        return ExportTable {
+            dll_name,
+            ordinal_base,
+            ..ExportTable::default()
+        };
     }
 
-    pub fn get_architecture(&self) -> PEArchitecture {
-        match &self.header {
-            PEHeader::PE32(_) => return PEArchitecture::PE32,
-            PEHeader::PE64(_) => return PEArchitecture::PE64,
+    pub fn add_symbol(&mut self, symbol: ExportedSymbol) {
+        let index = self.symbols.len();
+
+        if let Some(name) = &symbol.name {
+            self.names.entry(name.clone()).or_insert(index);
         }
+
+        self.ordinals.entry(symbol.ordinal).or_insert(index);
+        self.symbols.push(symbol);
     }
 
-    pub fn is_32_bits(&self) -> bool {
-        match &self.header {
-            PEHeader::PE32(_) => return true,
-            PEHeader::PE64(_) => return false,
-        }
+    pub fn get_symbols(&self) -> &[ExportedSymbol] {
+        return &self.symbols;
     }
 
-    pub fn get_size_of_optional_header(&self) -> u64 {
-        match &self.header {
-            PEHeader::PE32(header) => {
-                return header.nt.coff_header.size_of_optional_header as u64;
-            }
-            PEHeader::PE64(header) => {
-                return header.nt.coff_header.size_of_optional_header as u64;
-            }
+    pub fn find_by_name(&self, name: &str) -> Option<&ExportedSymbol> {
+        return self.names.get(name).map(|index| &self.symbols[*index]);
+    }
+
+    pub fn find_by_ordinal(&self, ordinal: u16) -> Option<&ExportedSymbol> {
+        return self
+            .ordinals
+            .get(&ordinal)
+            .map(|index| &self.symbols[*index]);
+    }
+
+    pub fn find(&self, symbol: &ImportedSymbol) -> Option<&ExportedSymbol> {
+        match symbol {
+            ImportedSymbol::ByName { name, .. } => return self.find_by_name(name),
+            ImportedSymbol::ByOrdinal(ordinal) => return self.find_by_ordinal(*ordinal),
         }
     }
+}
 
-    pub fn get_number_of_sections(&self) -> usize {
-        match &self.header {
-            PEHeader::PE32(header) => {
-                return header.nt.coff_header.number_of_sections as usize;
-            }
-            PEHeader::PE64(header) => {
-                return header.nt.coff_header.number_of_sections as usize;
-            }
-        }
+/*
+ * Debug Directory. Besides debug information, its entries carry the extended DLL characteristics
+ */
+
+pub const IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS: u32 = 20;
+
+/* Extended DLL characteristics */
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT: u32 = 0x1;
+
+const IMAGE_DEBUG_DIRECTORY_SIZE: u32 = 28;
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct ImageDebugDirectory {
+    characteristics: u32,
+    time_date_stamp: u32,
+    major_version: u16,
+    minor_version: u16,
+    pub debug_type: u32,
+    pub size_of_data: u32,
+    pub address_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+}
+
+impl ImageDebugDirectory {
+    pub fn new() -> ImageDebugDirectory {
+        return ImageDebugDirectory::default();
     }
 
-    pub fn get_import_table_idd(&self) -> ImageDataDirectory {
-        match &self.header {
-            PEHeader::PE32(header) => {
-                return header.optional.import_table.clone();
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<ImageDebugDirectory, PeError> {
+        let mut idd = ImageDebugDirectory::new();
+
+        idd.characteristics = cursor.read_dword()?;
+        idd.time_date_stamp = cursor.read_dword()?;
+        idd.major_version = cursor.read_word()?;
+        idd.minor_version = cursor.read_word()?;
+        idd.debug_type = cursor.read_dword()?;
+        idd.size_of_data = cursor.read_dword()?;
+        idd.address_of_raw_data = cursor.read_dword()?;
+        idd.pointer_to_raw_data = cursor.read_dword()?;
+
+        return Ok(idd);
+    }
+}
+
+/*
+ * Load Configuration Directory. Its layout depends on the bitness of the image, and it only
+ * holds the fields known to the linker that produced it, as given by its size
+ */
+
+/* Guard flags */
+pub const IMAGE_GUARD_CF_INSTRUMENTED: u32 = 0x100;
+
+#[derive(Default, Clone, Debug)]
+pub struct LoadConfigDirectory {
+    size: u32,
+    security_cookie: u64,
+    se_handler_table: u64,
+    se_handler_count: u64,
+    guard_cf_function_table: u64,
+    guard_flags: u32,
+    /* Set in hybrid images, whose header only gives the machine of their native code */
+    chpe_metadata_pointer: u64,
+}
+
+impl LoadConfigDirectory {
+    pub fn new() -> LoadConfigDirectory {
+        return LoadConfigDirectory::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut io::Cursor<&[u8]>,
+        is_32_bits: bool,
+    ) -> Result<LoadConfigDirectory, PeError> {
+        let mut lcd = LoadConfigDirectory::new();
+        let start = cursor.position();
+
+        lcd.size = cursor.read_dword()?;
+
+        /* Offsets of the fields, the pointer sized ones being 4 or 8 bytes long */
+        let (
+            pointer_size,
+            security_cookie,
+            se_handler_table,
+            guard_cf_function_table,
+            guard_flags,
+            chpe_metadata_pointer,
+        ) = match is_32_bits {
+            true => (4, 0x3c, 0x40, 0x50, 0x58, 0x7c),
+            false => (8, 0x58, 0x60, 0x80, 0x90, 0xc8),
+        };
+
+        let mut read_field = |offset: u64, size: u64| -> Result<u64, PeError> {
+            if offset + size > lcd.size as u64 {
+                return Ok(0);
             }
-            PEHeader::PE64(header) => {
-                return header.optional.import_table.clone();
+
+            cursor.set_position(start + offset);
+
+            match size {
+                4 => return Ok(cursor.read_dword()? as u64),
+                _ => return cursor.read_qword(),
             }
-        }
+        };
+
+        lcd.security_cookie = read_field(security_cookie, pointer_size)?;
+        lcd.se_handler_table = read_field(se_handler_table, pointer_size)?;
+        lcd.se_handler_count = read_field(se_handler_table + pointer_size, pointer_size)?;
+        lcd.guard_cf_function_table = read_field(guard_cf_function_table, pointer_size)?;
+        lcd.guard_flags = read_field(guard_flags, 4)? as u32;
+        lcd.chpe_metadata_pointer = read_field(chpe_metadata_pointer, pointer_size)?;
+
+        return Ok(lcd);
     }
 
-    pub fn convert_rva_to_file_offset(&self, rva: u32) -> Option<u64> {
-        for section in self.sections.values() {
-            let start = section.header.virtual_address;
-            let end = start + section.header.virtual_size;
+    pub fn get_security_cookie(&self) -> u64 {
+        return self.security_cookie;
+    }
 
-            if rva >= start && rva < end {
-                let offset_in_section = (rva - start) as u64;
-                return Some(section.header.ptr_to_raw_data as u64 + offset_in_section);
-            }
-        }
+    pub fn get_se_handler_table(&self) -> u64 {
+        return self.se_handler_table;
+    }
 
-        return None;
+    pub fn get_se_handler_count(&self) -> u64 {
+        return self.se_handler_count;
+    }
+
+    pub fn get_guard_cf_function_table(&self) -> u64 {
+        return self.guard_cf_function_table;
+    }
+
+    pub fn get_guard_flags(&self) -> u32 {
+        return self.guard_flags;
+    }
+
+    pub fn get_chpe_metadata_pointer(&self) -> u64 {
+        return self.chpe_metadata_pointer;
     }
 }
 
 /*
- * Parse import descriptors. Returns an empty vector if there are no import descriptors
+ * Resource Directory (.rsrc). The tree has three levels (types, names and languages), its leaves
+ * pointing to the data of each resource. Offsets inside the tree are relative to its start
  */
-fn parse_import_descriptors(
-    pe: &PE,
-    cursor: &mut io::Cursor<&Vec<u8>>,
-) -> Result<Vec<ImageImportDescriptor>, Box<dyn std::error::Error>> {
-    let mut descriptors: Vec<ImageImportDescriptor> = Vec::new();
 
-    let import_table_idd = pe.get_import_table_idd();
+pub const RT_VERSION: u16 = 16;
+pub const RT_MANIFEST: u16 = 24;
 
-    let file_offset = match pe.convert_rva_to_file_offset(import_table_idd.virtual_address) {
-        Some(offset) => offset,
-        _ => {
-            return Ok(descriptors);
-        }
-    };
+/*
+ * Manifest resources: the activation context of a process (or the manifest of an assembly shipped
+ * as a dll), the one a dll uses for its own static imports, and one for isolation aware code
+ * only, which the loader does not use
+ */
+pub const CREATEPROCESS_MANIFEST_RESOURCE_ID: u16 = 1;
+pub const ISOLATIONAWARE_MANIFEST_RESOURCE_ID: u16 = 2;
+pub const ISOLATIONAWARE_NOSTATICIMPORT_MANIFEST_RESOURCE_ID: u16 = 3;
 
-    cursor.set_position(file_offset as u64);
+/* High bit of the name of an entry, set when the entry is named instead of identified */
+const IMAGE_RESOURCE_NAME_IS_STRING: u32 = 0x80000000;
 
-    loop {
-        let descriptor = ImageImportDescriptor::from_parser(cursor)
-            .expect("Cannot parse ImageImportDescriptor from the Import Table");
+/* High bit of the offset of an entry, set when the entry points to a subdirectory */
+const IMAGE_RESOURCE_DATA_IS_DIRECTORY: u32 = 0x80000000;
 
-        if descriptor.is_zeroed_out() {
-            break;
-        }
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct ImageResourceDirectory {
+    characteristics: u32, /* reserved field */
+    time_date_stamp: u32,
+    major_version: u16,
+    minor_version: u16,
+    number_of_named_entries: u16,
+    number_of_id_entries: u16,
+}
 
-        descriptors.push(descriptor);
+impl ImageResourceDirectory {
+    pub fn new() -> ImageResourceDirectory {
+        return ImageResourceDirectory::default();
+    }
 
-        if descriptors.len() > 256 {
-            break;
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<ImageResourceDirectory, PeError> {
+        let mut directory = ImageResourceDirectory::new();
+
+        directory.characteristics = cursor.read_dword()?;
+        directory.time_date_stamp = cursor.read_dword()?;
+        directory.major_version = cursor.read_word()?;
+        directory.minor_version = cursor.read_word()?;
+        directory.number_of_named_entries = cursor.read_word()?;
+        directory.number_of_id_entries = cursor.read_word()?;
+
+        return Ok(directory);
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct ImageResourceDirectoryEntry {
+    name_or_id: u32,
+    offset_to_data: u32,
+}
+
+impl ImageResourceDirectoryEntry {
+    pub fn new() -> ImageResourceDirectoryEntry {
+        return ImageResourceDirectoryEntry::default();
+    }
+
+    pub fn from_parser(
+        cursor: &mut io::Cursor<&[u8]>,
+    ) -> Result<ImageResourceDirectoryEntry, PeError> {
+        let mut entry = ImageResourceDirectoryEntry::new();
+
+        entry.name_or_id = cursor.read_dword()?;
+        entry.offset_to_data = cursor.read_dword()?;
+
+        return Ok(entry);
+    }
+
+    pub fn is_named(&self) -> bool {
+        return self.name_or_id & IMAGE_RESOURCE_NAME_IS_STRING != 0;
+    }
+
+    pub fn is_directory(&self) -> bool {
+        return self.offset_to_data & IMAGE_RESOURCE_DATA_IS_DIRECTORY != 0;
+    }
+
+    pub fn get_name_offset(&self) -> u64 {
+        return (self.name_or_id & !IMAGE_RESOURCE_NAME_IS_STRING) as u64;
+    }
+
+    pub fn get_data_offset(&self) -> u64 {
+        return (self.offset_to_data & !IMAGE_RESOURCE_DATA_IS_DIRECTORY) as u64;
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct ImageResourceDataEntry {
+    data_rva: u32,
+    size: u32,
+    code_page: u32,
+    reserved: u32, /* reserved field */
+}
+
+impl ImageResourceDataEntry {
+    pub fn new() -> ImageResourceDataEntry {
+        return ImageResourceDataEntry::default();
+    }
+
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<ImageResourceDataEntry, PeError> {
+        let mut entry = ImageResourceDataEntry::new();
+
+        entry.data_rva = cursor.read_dword()?;
+        entry.size = cursor.read_dword()?;
+        entry.code_page = cursor.read_dword()?;
+        entry.reserved = cursor.read_dword()?;
+
+        return Ok(entry);
+    }
+}
+
+/*
+ * Types, names and languages of resources are either integer identifiers or utf-16 strings
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResourceId {
+    Id(u16),
+    Name(String),
+}
+
+impl Default for ResourceId {
+    fn default() -> Self {
+        return ResourceId::Id(0);
+    }
+}
+
+impl std::fmt::Display for ResourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceId::Id(id) => return write!(f, "#{id}"),
+            ResourceId::Name(name) => return write!(f, "{name}"),
         }
     }
+}
 
-    return Ok(descriptors);
+/*
+ * Leaf of the resource tree
+ */
+#[derive(Default, Clone, Debug)]
+pub struct Resource {
+    pub resource_type: ResourceId,
+    pub name: ResourceId,
+    pub language: u16,
+    pub data_rva: u32,
+    pub size: u32,
+    pub code_page: u32,
 }
 
 /*
- * Parse dll names
+ * Version resource (RT_VERSION). VS_VERSIONINFO and its children (StringFileInfo, StringTable,
+ * String, VarFileInfo) are blocks sharing the same header: their length, the length of their value,
+ * the type of their value, and a utf-16 key. The value and the children are aligned on 32 bits
  */
-fn parse_dll_names(
-    pe: &PE,
-    cursor: &mut io::Cursor<&Vec<u8>>,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let mut dlls: Vec<String> = Vec::new();
 
-    for import_descriptor in &pe.import_descriptors {
-        cursor.set_position(
-            pe.convert_rva_to_file_offset(import_descriptor.name_rva)
-                .ok_or("Import Descriptor Name RVA does not map to any section")?,
-        );
+const VS_FIXEDFILEINFO_SIGNATURE: u32 = 0xfeef04bd;
 
-        let mut name_buffer: Vec<u8> = Vec::new();
+/* Type of version blocks whose value length is given in utf-16 characters */
+const VERSION_BLOCK_TEXT: u16 = 1;
 
-        loop {
-            let c = cursor.read_u8()?;
+fn align_on_dword(position: u64) -> u64 {
+    return (position + 3) & !3;
+}
 
-            if c == 0x0 {
-                break;
-            }
+struct VersionBlock {
+    start: u64,
+    length: u16,
+    value_length: u16,
+    value_type: u16,
+    key: String,
+    value_position: u64,
+}
 
-            name_buffer.push(c);
+impl VersionBlock {
+    fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<VersionBlock, PeError> {
+        let start = cursor.position();
+        let length = cursor.read_word()?;
+        let value_length = cursor.read_word()?;
+        let value_type = cursor.read_word()?;
+
+        if length < 6 {
+            return Err(PeError::Malformed {
+                offset: start,
+                reason: "Version block is too small",
+            });
         }
 
-        dlls.push(String::from_utf8(name_buffer).expect("Invalid name found in import names"));
+        let key = read_utf16_null_terminated_string(cursor, start + length as u64)?;
+        let value_position = align_on_dword(cursor.position());
+
+        return Ok(VersionBlock {
+            start,
+            length,
+            value_length,
+            value_type,
+            key,
+            value_position,
+        });
     }
 
-    return Ok(dlls);
+    fn get_end(&self) -> u64 {
+        return self.start + self.length as u64;
+    }
+
+    fn get_value_size(&self) -> u64 {
+        if self.value_type == VERSION_BLOCK_TEXT {
+            return self.value_length as u64 * 2;
+        }
+
+        return self.value_length as u64;
+    }
+
+    fn read_children(&self, cursor: &mut io::Cursor<&[u8]>) -> Result<Vec<VersionBlock>, PeError> {
+        let mut children: Vec<VersionBlock> = Vec::new();
+        let mut position = align_on_dword(self.value_position + self.get_value_size());
+
+        while position < self.get_end() {
+            cursor.set_position(position);
+
+            let child = VersionBlock::from_parser(cursor)?;
+
+            position = align_on_dword(child.get_end());
+            children.push(child);
+        }
+
+        return Ok(children);
+    }
 }
 
-/*
- * Main parse method that reads from a file, tests if it's a PE file or not, and returns the parsed PE
- */
-pub fn parse_pe(file_path: &PathBuf) -> Result<PE, Box<dyn std::error::Error>> {
-    if !file_path.exists() {
-        return Err("File does not exist".into());
+#[derive(Default, Clone, Debug)]
+#[repr(C)]
+pub struct VSFixedFileInfo {
+    signature: u32,
+    struc_version: u32,
+    file_version_ms: u32,
+    file_version_ls: u32,
+    product_version_ms: u32,
+    product_version_ls: u32,
+    file_flags_mask: u32,
+    file_flags: u32,
+    file_os: u32,
+    file_type: u32,
+    file_subtype: u32,
+    file_date_ms: u32,
+    file_date_ls: u32,
+}
+
+impl VSFixedFileInfo {
+    pub fn new() -> VSFixedFileInfo {
+        return VSFixedFileInfo::default();
     }
 
-    let file_path_str: &str = file_path.to_str().expect("Cannot convert file_path to str");
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<VSFixedFileInfo, PeError> {
+        let mut info = VSFixedFileInfo::new();
+
+        let signature_position = cursor.position();
+
+        info.signature = cursor.read_dword()?;
 
-    if !file_path_str.ends_with(".exe") && !file_path_str.ends_with(".dll") {
-        return Err("File is not a Portable Executable (.exe | .dll)".into());
+        if info.signature != VS_FIXEDFILEINFO_SIGNATURE {
+            return Err(PeError::BadMagic {
+                offset: signature_position,
+                what: "VS_FIXEDFILEINFO signature",
+                found: info.signature,
+            });
+        }
+
+        info.struc_version = cursor.read_dword()?;
+        info.file_version_ms = cursor.read_dword()?;
+        info.file_version_ls = cursor.read_dword()?;
+        info.product_version_ms = cursor.read_dword()?;
+        info.product_version_ls = cursor.read_dword()?;
+        info.file_flags_mask = cursor.read_dword()?;
+        info.file_flags = cursor.read_dword()?;
+        info.file_os = cursor.read_dword()?;
+        info.file_type = cursor.read_dword()?;
+        info.file_subtype = cursor.read_dword()?;
+        info.file_date_ms = cursor.read_dword()?;
+        info.file_date_ls = cursor.read_dword()?;
+
+        return Ok(info);
     }
 
-    let file_bytes = std::fs::read(file_path).expect("Unable to open file");
+    fn format_version(ms: u32, ls: u32) -> String {
+        return format!("{}.{}.{}.{}", ms >> 16, ms & 0xffff, ls >> 16, ls & 0xffff);
+    }
 
-    let mut pe: PE = PE::new();
-    pe.data = file_bytes;
+    pub fn get_file_version(&self) -> String {
+        return VSFixedFileInfo::format_version(self.file_version_ms, self.file_version_ls);
+    }
 
-    let mut cursor = io::Cursor::new(&pe.data);
+    pub fn get_product_version(&self) -> String {
+        return VSFixedFileInfo::format_version(self.product_version_ms, self.product_version_ls);
+    }
 
-    let dos_header = DOSHeader::from_parser(&mut cursor)?;
+    /* Flags that are set and valid (VS_FF_DEBUG, VS_FF_PRERELEASE, ...) */
+    pub fn get_file_flags(&self) -> u32 {
+        return self.file_flags & self.file_flags_mask;
+    }
 
-    cursor.set_position(dos_header.lfanew as u64);
+    pub fn get_file_os(&self) -> u32 {
+        return self.file_os;
+    }
 
-    let nt_header = NTHeader::from_parser(&mut cursor)?;
+    pub fn get_file_type(&self) -> u32 {
+        return self.file_type;
+    }
 
-    let optional_magic: u16 = cursor.read_u16::<LittleEndian>()?;
-    cursor.set_position(cursor.position() - 2);
+    pub fn get_file_subtype(&self) -> u32 {
+        return self.file_subtype;
+    }
+}
 
-    let start_of_optional_position = cursor.position();
+/*
+ * Decoded version resource: the fixed file info, and the StringFileInfo values (CompanyName,
+ * FileVersion, ProductName, ...). When there are several string tables (languages), the first value
+ * found for a key is kept
+ */
+#[derive(Default, Clone, Debug)]
+pub struct VersionInfo {
+    pub fixed_file_info: Option<VSFixedFileInfo>,
+    pub strings: Vec<(String, String)>,
+}
 
-    match optional_magic {
-        PE_FORMAT_32_MAGIC => {
-            let optional_header: OptionalHeader32 = OptionalHeader32::from_parser(&mut cursor)?;
+impl VersionInfo {
+    pub fn new() -> VersionInfo {
+        return VersionInfo::default();
+    }
 
-            pe.header = PEHeader::PE32(PE32Header {
-                dos: dos_header,
-                nt: nt_header,
-                optional: optional_header,
+    /* The cursor reads the data of the resource */
+    pub fn from_parser(cursor: &mut io::Cursor<&[u8]>) -> Result<VersionInfo, PeError> {
+        let mut version_info = VersionInfo::new();
+
+        let root = VersionBlock::from_parser(cursor)?;
+
+        if root.key != "VS_VERSION_INFO" {
+            return Err(PeError::Malformed {
+                offset: root.start,
+                reason: "Invalid VS_VERSIONINFO key",
             });
         }
-        PE_FORMAT_64_MAGIC => {
-            let optional_header: OptionalHeader64 = OptionalHeader64::from_parser(&mut cursor)?;
 
-            pe.header = PEHeader::PE64(PE64Header {
-                dos: dos_header,
-                nt: nt_header,
-                optional: optional_header,
-            });
+        if root.value_length as usize >= std::mem::size_of::<VSFixedFileInfo>() {
+            cursor.set_position(root.value_position);
+            version_info.fixed_file_info = Some(VSFixedFileInfo::from_parser(cursor)?);
         }
-        _ => {
-            return Err("Invalid PE optional header magic".into());
+
+        for file_info in root.read_children(cursor)? {
+            if file_info.key != "StringFileInfo" {
+                continue;
+            }
+
+            for string_table in file_info.read_children(cursor)? {
+                for string in string_table.read_children(cursor)? {
+                    cursor.set_position(string.value_position);
+
+                    let value = read_utf16_null_terminated_string(
+                        cursor,
+                        (string.value_position + string.get_value_size()).min(string.get_end()),
+                    )?;
+
+                    if !version_info
+                        .strings
+                        .iter()
+                        .any(|(key, _)| *key == string.key)
+                    {
+                        version_info.strings.push((string.key, value));
+                    }
+                }
+            }
         }
+
+        return Ok(version_info);
     }
 
-    let end_of_optional_position = cursor.position();
-    let optional_size = end_of_optional_position - start_of_optional_position;
+    pub fn get_string(&self, key: &str) -> Option<&str> {
+        return self
+            .strings
+            .iter()
+            .find(|(string_key, _)| string_key == key)
+            .map(|(_, value)| value.as_str());
+    }
+}
 
-    cursor.set_position(cursor.position() + (pe.get_size_of_optional_header() - optional_size));
+/*
+ * PE Header
+ */
 
-    for _ in 0..pe.get_number_of_sections() {
-        let section_header = SectionHeader::from_parser(&mut cursor)?;
+#[allow(dead_code)]
+#[derive(Default, Clone, Debug)]
+pub struct PE32Header {
+    dos: DOSHeader,
+    nt: NTHeader,
+    optional: OptionalHeader32,
+}
 
-        pe.sections.insert(
-            section_header.name.clone(),
-            Section {
-                header: section_header,
-            },
-        );
-    }
+#[allow(dead_code)]
+#[derive(Default, Clone, Debug)]
+pub struct PE64Header {
+    dos: DOSHeader,
+    nt: NTHeader,
+    optional: OptionalHeader64,
+}
 
-    pe.import_descriptors = parse_import_descriptors(&pe, &mut cursor)?;
-    pe.dll_names = parse_dll_names(&pe, &mut cursor)?;
+#[derive(Clone, Debug)]
+pub enum PEHeader {
+    PE32(PE32Header),
+    PE64(PE64Header),
+}
 
-    return Ok(pe);
+impl Default for PEHeader {
+    fn default() -> PEHeader {
+        return PEHeader::PE32(PE32Header::default());
+    }
+}
+
+/*
+ * Dll imported by a PE, either at load time or through the delay-load helper
+ */
+#[derive(Default, Clone, Debug)]
+pub struct ImportedDll {
+    pub name: String,
+    pub symbols: Vec<ImportedSymbol>,
+    pub delay_load: bool,
+}
+
+/*
+ * Raw bytes of a PE. The parser only needs a &[u8] view, so when the file is memory mapped, only
+ * the pages that are actually read (headers, import and export tables, ...) are loaded
+ */
+pub enum PEData {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl PEData {
+    pub fn read(file_path: &Path) -> io::Result<PEData> {
+        #[cfg(feature = "mmap")]
+        {
+            let file = std::fs::File::open(file_path)?;
+
+            /*
+             * Safety: the mapping is read-only, and files are not expected to be modified while
+             * fdw walks them. Some files cannot be mapped (empty files, special files, ...), and
+             * are read in memory instead
+             */
+            if let Ok(mapping) = unsafe { memmap2::Mmap::map(&file) } {
+                return Ok(PEData::Mapped(mapping));
+            }
+        }
+
+        return Ok(PEData::Owned(std::fs::read(file_path)?));
+    }
+}
+
+impl Default for PEData {
+    fn default() -> PEData {
+        return PEData::Owned(Vec::new());
+    }
+}
+
+impl std::ops::Deref for PEData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            PEData::Owned(bytes) => return bytes,
+            #[cfg(feature = "mmap")]
+            PEData::Mapped(mapping) => return mapping,
+        }
+    }
+}
+
+impl std::fmt::Debug for PEData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PEData::Owned(bytes) => return write!(f, "Owned({} bytes)", bytes.len()),
+            #[cfg(feature = "mmap")]
+            PEData::Mapped(mapping) => return write!(f, "Mapped({} bytes)", mapping.len()),
+        }
+    }
+}
+
+/*
+ * PE
+ */
+
+pub enum PEArchitecture {
+    PE32,
+    PE64,
+}
+
+/*
+ * Only the headers and the section table are parsed upfront. Directories are parsed the first time
+ * they are accessed, so that a query only reads the pages it needs from a mapped file
+ */
+#[derive(Default, Debug)]
+pub struct PE {
+    pub header: PEHeader,
+    pub sections: HashMap<String, Section>,
+    pub data: PEData,
+    imports: OnceCell<Result<Vec<ImportedDll>, PeError>>,
+    delay_imports: OnceCell<Vec<ImportedDll>>,
+    exports: OnceCell<Option<ExportTable>>,
+    resources: OnceCell<Vec<Resource>>,
+    debug_directories: OnceCell<Vec<ImageDebugDirectory>>,
+    load_config: OnceCell<Option<LoadConfigDirectory>>,
+}
+
+impl PE {
+    pub fn new() -> PE {
+        return PE::default();
+    }
+
+    pub fn get_architecture(&self) -> PEArchitecture {
+        match &self.header {
+            PEHeader::PE32(_) => return PEArchitecture::PE32,
+            PEHeader::PE64(_) => return PEArchitecture::PE64,
+        }
+    }
+
+    pub fn is_32_bits(&self) -> bool {
+        match &self.header {
+            PEHeader::PE32(_) => return true,
+            PEHeader::PE64(_) => return false,
+        }
+    }
+
+    /*
+     * Hybrid images carry the machine of their native code in the header: x64 for ARM64EC images
+     * and ARM64 for ARM64X ones. They are told apart by the CHPE metadata of their load config
+     */
+    pub fn get_machine(&self) -> Machine {
+        let machine = match &self.header {
+            PEHeader::PE32(header) => Machine::from_u16(header.nt.coff_header.machine),
+            PEHeader::PE64(header) => Machine::from_u16(header.nt.coff_header.machine),
+        };
+
+        let is_hybrid = self
+            .get_load_config()
+            .is_some_and(|load_config| load_config.get_chpe_metadata_pointer() != 0);
+
+        return match (machine, is_hybrid) {
+            (Machine::Amd64, true) => Machine::Arm64Ec,
+            (Machine::Arm64, true) => Machine::Arm64X,
+            _ => machine,
+        };
+    }
+
+    pub fn get_characteristics(&self) -> Characteristics {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return Characteristics::from_bits(header.nt.coff_header.characteristics);
+            }
+            PEHeader::PE64(header) => {
+                return Characteristics::from_bits(header.nt.coff_header.characteristics);
+            }
+        }
+    }
+
+    pub fn get_subsystem(&self) -> Subsystem {
+        match &self.header {
+            PEHeader::PE32(header) => return Subsystem::from_u16(header.optional.subsystem),
+            PEHeader::PE64(header) => return Subsystem::from_u16(header.optional.subsystem),
+        }
+    }
+
+    pub fn get_dll_characteristics(&self) -> DllCharacteristics {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return DllCharacteristics::from_bits(header.optional.dll_characteristics);
+            }
+            PEHeader::PE64(header) => {
+                return DllCharacteristics::from_bits(header.optional.dll_characteristics);
+            }
+        }
+    }
+
+    pub fn is_dll(&self) -> bool {
+        return self.get_characteristics().contains(Characteristics::DLL);
+    }
+
+    pub fn get_size_of_optional_header(&self) -> u64 {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.nt.coff_header.size_of_optional_header as u64;
+            }
+            PEHeader::PE64(header) => {
+                return header.nt.coff_header.size_of_optional_header as u64;
+            }
+        }
+    }
+
+    /*
+     * The COFF string table immediately follows the symbol table, each symbol being 18 bytes
+     */
+    pub fn get_string_table_offset(&self) -> Option<u64> {
+        let coff_header = match &self.header {
+            PEHeader::PE32(header) => &header.nt.coff_header,
+            PEHeader::PE64(header) => &header.nt.coff_header,
+        };
+
+        if coff_header.pointer_to_symbol_table == 0 {
+            return None;
+        }
+
+        return Some(
+            coff_header.pointer_to_symbol_table as u64 + coff_header.number_of_symbols as u64 * 18,
+        );
+    }
+
+    pub fn get_number_of_sections(&self) -> usize {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.nt.coff_header.number_of_sections as usize;
+            }
+            PEHeader::PE64(header) => {
+                return header.nt.coff_header.number_of_sections as usize;
+            }
+        }
+    }
+
+    pub fn get_import_table_idd(&self) -> ImageDataDirectory {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.optional.import_table.clone();
+            }
+            PEHeader::PE64(header) => {
+                return header.optional.import_table.clone();
+            }
+        }
+    }
+
+    pub fn get_delay_import_descriptor_idd(&self) -> ImageDataDirectory {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.optional.delay_import_descriptor.clone();
+            }
+            PEHeader::PE64(header) => {
+                return header.optional.delay_import_descriptor.clone();
+            }
+        }
+    }
+
+    pub fn get_image_base(&self) -> u64 {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.optional.image_base as u64;
+            }
+            PEHeader::PE64(header) => {
+                return header.optional.image_base;
+            }
+        }
+    }
+
+    fn get_cursor(&self) -> io::Cursor<&[u8]> {
+        return io::Cursor::new(&self.data[..]);
+    }
+
+    /*
+     * Dlls imported at load time. The loader cannot map an image with a malformed import table, so
+     * unlike the optional directories, errors are returned
+     */
+    pub fn get_imports(&self) -> Result<&[ImportedDll], &PeError> {
+        return self
+            .imports
+            .get_or_init(|| parse_imports(self, &mut self.get_cursor()))
+            .as_deref();
+    }
+
+    pub fn get_delay_imports(&self) -> &[ImportedDll] {
+        return self.delay_imports.get_or_init(|| {
+            parse_optional_directory(
+                "delay-load import table",
+                parse_delay_imports(self, &mut self.get_cursor()),
+            )
+        });
+    }
+
+    /*
+     * Returns the imported dlls, regular imports first and then delay-loaded ones
+     */
+    pub fn get_imported_dlls(&self) -> Result<Vec<&ImportedDll>, &PeError> {
+        return Ok(self
+            .get_imports()?
+            .iter()
+            .chain(self.get_delay_imports())
+            .collect());
+    }
+
+    /* None if the PE does not export anything */
+    pub fn get_exports(&self) -> Option<&ExportTable> {
+        return self
+            .exports
+            .get_or_init(|| {
+                parse_optional_directory(
+                    "export table",
+                    parse_exports(self, &mut self.get_cursor()),
+                )
+            })
+            .as_ref();
+    }
+
+    pub fn get_resources(&self) -> &[Resource] {
+        return self.resources.get_or_init(|| {
+            parse_optional_directory(
+                "resource directory",
+                parse_resources(self, &mut self.get_cursor()),
+            )
+        });
+    }
+
+    pub fn get_debug_directories(&self) -> &[ImageDebugDirectory] {
+        return self.debug_directories.get_or_init(|| {
+            parse_optional_directory(
+                "debug directory",
+                parse_debug_directories(self, &mut self.get_cursor()),
+            )
+        });
+    }
+
+    pub fn get_load_config(&self) -> Option<&LoadConfigDirectory> {
+        return self
+            .load_config
+            .get_or_init(|| {
+                parse_optional_directory(
+                    "load configuration directory",
+                    parse_load_config(self, &mut self.get_cursor()),
+                )
+            })
+            .as_ref();
+    }
+
+    pub fn get_export_table_idd(&self) -> ImageDataDirectory {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.optional.export_table.clone();
+            }
+            PEHeader::PE64(header) => {
+                return header.optional.export_table.clone();
+            }
+        }
+    }
+
+    pub fn get_resource_table_idd(&self) -> ImageDataDirectory {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.optional.resource_table.clone();
+            }
+            PEHeader::PE64(header) => {
+                return header.optional.resource_table.clone();
+            }
+        }
+    }
+
+    pub fn get_debug_idd(&self) -> ImageDataDirectory {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.optional.debug.clone();
+            }
+            PEHeader::PE64(header) => {
+                return header.optional.debug.clone();
+            }
+        }
+    }
+
+    pub fn get_load_config_table_idd(&self) -> ImageDataDirectory {
+        match &self.header {
+            PEHeader::PE32(header) => {
+                return header.optional.load_config_table.clone();
+            }
+            PEHeader::PE64(header) => {
+                return header.optional.load_config_table.clone();
+            }
+        }
+    }
+
+    /*
+     * Extended DLL characteristics (IMAGE_DLLCHARACTERISTICS_EX_*), 0 without the debug entry
+     */
+    pub fn get_ex_dll_characteristics(&self) -> u32 {
+        let Some(debug_directory) = self
+            .get_debug_directories()
+            .iter()
+            .find(|idd| idd.debug_type == IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS)
+        else {
+            return 0;
+        };
+
+        let mut cursor = self.get_cursor();
+        cursor.set_position(debug_directory.pointer_to_raw_data as u64);
+
+        return cursor.read_dword().unwrap_or(0);
+    }
+
+    pub fn find_resources(&self, resource_type: u16) -> Vec<&Resource> {
+        return self
+            .get_resources()
+            .iter()
+            .filter(|resource| resource.resource_type == ResourceId::Id(resource_type))
+            .collect();
+    }
+
+    /* Raw bytes of a resource, None if they are not in the file */
+    pub fn get_resource_data(&self, resource: &Resource) -> Option<&[u8]> {
+        let start = self.convert_rva_to_file_offset(resource.data_rva)? as usize;
+        let end = start.checked_add(resource.size as usize)?;
+
+        return self.data.get(start..end);
+    }
+
+    /*
+     * Decodes the first version resource. A malformed version resource is logged and ignored, as it
+     * does not prevent the PE from being loaded
+     */
+    pub fn get_version_info(&self) -> Option<VersionInfo> {
+        let resource = *self.find_resources(RT_VERSION).first()?;
+        let data = self.get_resource_data(resource)?;
+
+        match VersionInfo::from_parser(&mut io::Cursor::new(data)) {
+            Ok(version_info) => return Some(version_info),
+            Err(err) => {
+                log::debug!("Invalid version resource: {err}");
+                return None;
+            }
+        }
+    }
+
+    pub fn convert_rva_to_file_offset(&self, rva: u32) -> Option<u64> {
+        for section in self.sections.values() {
+            let start = section.header.virtual_address;
+            let end = start.saturating_add(section.header.virtual_size);
+
+            if rva >= start && rva < end {
+                let offset_in_section = (rva - start) as u64;
+                return Some(section.header.ptr_to_raw_data as u64 + offset_in_section);
+            }
+        }
+
+        return None;
+    }
+}
+
+/*
+ * Parse import descriptors. Returns an empty vector if there are no import descriptors. The loader
+ * cannot map an image whose import table is outside of its sections, so neither can the parser
+ */
+fn parse_import_descriptors(
+    pe: &PE,
+    cursor: &mut io::Cursor<&[u8]>,
+) -> Result<Vec<ImageImportDescriptor>, PeError> {
+    let mut descriptors: Vec<ImageImportDescriptor> = Vec::new();
+
+    let import_table_idd = pe.get_import_table_idd();
+
+    if import_table_idd.virtual_address == 0 {
+        return Ok(descriptors);
+    }
+
+    let file_offset = pe
+        .convert_rva_to_file_offset(import_table_idd.virtual_address)
+        .ok_or(PeError::RvaOutOfRange {
+            rva: import_table_idd.virtual_address,
+            what: "Import Table",
+        })?;
+
+    cursor.set_position(file_offset);
+
+    loop {
+        let descriptor = ImageImportDescriptor::from_parser(cursor)?;
+
+        if descriptor.is_zeroed_out() {
+            break;
+        }
+
+        descriptors.push(descriptor);
+
+        if descriptors.len() > 256 {
+            break;
+        }
+    }
+
+    return Ok(descriptors);
+}
+
+/*
+ * The loader maps an image whatever the state of the directories it does not need, so a malformed
+ * optional directory is logged and ignored instead of failing the whole PE
+ */
+fn parse_optional_directory<T: Default>(what: &str, result: Result<T, PeError>) -> T {
+    match result {
+        Ok(directory) => return directory,
+        Err(err) => {
+            log::debug!("Invalid {what}: {err}");
+            return T::default();
+        }
+    }
+}
+
+/*
+ * Parse the import descriptors with the names of their dlls and their imported symbols
+ */
+fn parse_imports(pe: &PE, cursor: &mut io::Cursor<&[u8]>) -> Result<Vec<ImportedDll>, PeError> {
+    let descriptors = parse_import_descriptors(pe, cursor)?;
+    let dll_names = parse_dll_names(pe, &descriptors, cursor)?;
+
+    let mut dlls: Vec<ImportedDll> = Vec::new();
+
+    for (name, descriptor) in dll_names.into_iter().zip(&descriptors) {
+        dlls.push(ImportedDll {
+            name,
+            symbols: parse_imported_symbols(pe, cursor, descriptor.get_lookup_table_rva(), 0)?,
+            delay_load: false,
+        });
+    }
+
+    return Ok(dlls);
+}
+
+/*
+ * Parse the symbols imported through an Import Lookup Table, until the null entry
+ */
+fn parse_imported_symbols(
+    pe: &PE,
+    cursor: &mut io::Cursor<&[u8]>,
+    lookup_table_rva: u32,
+    va_bias: u32,
+) -> Result<Vec<ImportedSymbol>, PeError> {
+    let mut symbols: Vec<ImportedSymbol> = Vec::new();
+
+    let mut entry_offset = match pe.convert_rva_to_file_offset(lookup_table_rva) {
+        Some(offset) => offset,
+        _ => {
+            return Ok(symbols);
+        }
+    };
+
+    let entry_size: u64 = if pe.is_32_bits() { 4 } else { 8 };
+
+    loop {
+        cursor.set_position(entry_offset);
+
+        let mut entry = ImportLookupEntry::from_parser(cursor, pe.is_32_bits())?;
+
+        if entry.is_zeroed_out() {
+            break;
+        }
+
+        if !entry.by_ordinal {
+            entry.hint_name_table_rva = entry.hint_name_table_rva.wrapping_sub(va_bias);
+        }
+
+        symbols.push(ImportedSymbol::from_lookup_entry(&entry, pe, cursor)?);
+
+        entry_offset += entry_size;
+    }
+
+    return Ok(symbols);
+}
+
+/*
+ * Parse delay-load descriptors, converting VA-based descriptors to RVAs. Returns an empty vector
+ * if there are no delay-load descriptors
+ */
+fn parse_delay_import_descriptors(
+    pe: &PE,
+    cursor: &mut io::Cursor<&[u8]>,
+) -> Result<Vec<ImageDelayLoadDescriptor>, PeError> {
+    let mut descriptors: Vec<ImageDelayLoadDescriptor> = Vec::new();
+
+    let delay_import_idd = pe.get_delay_import_descriptor_idd();
+
+    if delay_import_idd.virtual_address == 0 {
+        return Ok(descriptors);
+    }
+
+    let file_offset = match pe.convert_rva_to_file_offset(delay_import_idd.virtual_address) {
+        Some(offset) => offset,
+        _ => {
+            return Ok(descriptors);
+        }
+    };
+
+    cursor.set_position(file_offset);
+
+    /*
+     * The table ends with a null descriptor, that some linkers leave out of the directory size, so
+     * only the descriptors that are not null have to fit in the directory
+     */
+    let max_descriptors = (delay_import_idd.size / IMAGE_DELAY_LOAD_DESCRIPTOR_SIZE) as usize;
+
+    loop {
+        let descriptor_offset = cursor.position();
+        let mut descriptor = ImageDelayLoadDescriptor::from_parser(cursor)?;
+
+        if descriptor.is_zeroed_out() {
+            break;
+        }
+
+        if descriptors.len() == max_descriptors {
+            return Err(PeError::Malformed {
+                offset: descriptor_offset,
+                reason: "Delay-load descriptor table is not terminated within its directory",
+            });
+        }
+
+        if !descriptor.is_rva_based() {
+            let image_base = pe.get_image_base() as u32;
+
+            descriptor.name_rva = descriptor.name_rva.wrapping_sub(image_base);
+            descriptor.import_name_table_rva =
+                descriptor.import_name_table_rva.wrapping_sub(image_base);
+        }
+
+        descriptors.push(descriptor);
+    }
+
+    return Ok(descriptors);
+}
+
+/*
+ * Parse delay-loaded dll names
+ */
+fn parse_delay_dll_names(
+    pe: &PE,
+    descriptors: &[ImageDelayLoadDescriptor],
+    cursor: &mut io::Cursor<&[u8]>,
+) -> Result<Vec<String>, PeError> {
+    let mut dlls: Vec<String> = Vec::new();
+
+    for delay_import_descriptor in descriptors {
+        cursor.set_position(
+            pe.convert_rva_to_file_offset(delay_import_descriptor.name_rva)
+                .ok_or(PeError::RvaOutOfRange {
+                    rva: delay_import_descriptor.name_rva,
+                    what: "Delay Load Descriptor Name",
+                })?,
+        );
+
+        dlls.push(read_null_terminated_string(cursor)?);
+    }
+
+    return Ok(dlls);
+}
+
+/*
+ * Parse the delay-load descriptors with the names of their dlls and their imported symbols
+ */
+fn parse_delay_imports(
+    pe: &PE,
+    cursor: &mut io::Cursor<&[u8]>,
+) -> Result<Vec<ImportedDll>, PeError> {
+    let descriptors = parse_delay_import_descriptors(pe, cursor)?;
+    let dll_names = parse_delay_dll_names(pe, &descriptors, cursor)?;
+
+    let mut dlls: Vec<ImportedDll> = Vec::new();
+
+    for (name, descriptor) in dll_names.into_iter().zip(&descriptors) {
+        let va_bias = match descriptor.is_rva_based() {
+            true => 0,
+            false => pe.get_image_base() as u32,
+        };
+
+        dlls.push(ImportedDll {
+            name,
+            symbols: parse_imported_symbols(pe, cursor, descriptor.import_name_table_rva, va_bias)?,
+            delay_load: true,
+        });
+    }
+
+    return Ok(dlls);
+}
+
+/*
+ * Parse dll names
+ */
+fn parse_dll_names(
+    pe: &PE,
+    descriptors: &[ImageImportDescriptor],
+    cursor: &mut io::Cursor<&[u8]>,
+) -> Result<Vec<String>, PeError> {
+    let mut dlls: Vec<String> = Vec::new();
+
+    for import_descriptor in descriptors {
+        cursor.set_position(
+            pe.convert_rva_to_file_offset(import_descriptor.name_rva)
+                .ok_or(PeError::RvaOutOfRange {
+                    rva: import_descriptor.name_rva,
+                    what: "Import Descriptor Name",
+                })?,
+        );
+
+        dlls.push(read_null_terminated_string(cursor)?);
+    }
+
+    return Ok(dlls);
+}
+
+/*
+ * Parse the export table. Returns None if the PE does not export anything
+ */
+fn parse_exports(pe: &PE, cursor: &mut io::Cursor<&[u8]>) -> Result<Option<ExportTable>, PeError> {
+    let export_table_idd = pe.get_export_table_idd();
+
+    if export_table_idd.virtual_address == 0 || export_table_idd.size == 0 {
+        return Ok(None);
+    }
+
+    let file_offset = match pe.convert_rva_to_file_offset(export_table_idd.virtual_address) {
+        Some(offset) => offset,
+        _ => {
+            return Ok(None);
+        }
+    };
+
+    cursor.set_position(file_offset);
+
+    let directory = ImageExportDirectory::from_parser(cursor)?;
+
+    let mut dll_name = String::new();
+
+    if let Some(name_offset) = pe.convert_rva_to_file_offset(directory.name_rva) {
+        cursor.set_position(name_offset);
+        dll_name = read_null_terminated_string(cursor)?;
+    }
+
+    let mut exports = ExportTable::new(dll_name, directory.ordinal_base);
+
+    let functions_offset = pe
+        .convert_rva_to_file_offset(directory.address_of_functions)
+        .ok_or(PeError::RvaOutOfRange {
+            rva: directory.address_of_functions,
+            what: "Export Address Table",
+        })?;
+
+    let mut function_rvas: Vec<u32> = Vec::new();
+
+    cursor.set_position(functions_offset);
+
+    for _ in 0..directory.number_of_functions {
+        function_rvas.push(cursor.read_dword()?);
+    }
+
+    /* Names are attached to functions through the ordinal table, which holds unbiased indices */
+    let mut function_names: Vec<Option<String>> = vec![None; function_rvas.len()];
+
+    if directory.number_of_names > 0 {
+        let names_offset = pe
+            .convert_rva_to_file_offset(directory.address_of_names)
+            .ok_or(PeError::RvaOutOfRange {
+                rva: directory.address_of_names,
+                what: "Export Name Pointer Table",
+            })?;
+        let ordinals_offset = pe
+            .convert_rva_to_file_offset(directory.address_of_name_ordinals)
+            .ok_or(PeError::RvaOutOfRange {
+                rva: directory.address_of_name_ordinals,
+                what: "Export Ordinal Table",
+            })?;
+
+        for i in 0..directory.number_of_names as u64 {
+            cursor.set_position(names_offset + i * 4);
+            let name_rva = cursor.read_dword()?;
+
+            cursor.set_position(ordinals_offset + i * 2);
+            let function_index = cursor.read_word()? as usize;
+
+            if function_index >= function_names.len() {
+                log::debug!("Export name ordinal {function_index} is out of the address table");
+                continue;
+            }
+
+            cursor.set_position(pe.convert_rva_to_file_offset(name_rva).ok_or(
+                PeError::RvaOutOfRange {
+                    rva: name_rva,
+                    what: "Export name",
+                },
+            )?);
+
+            function_names[function_index] = Some(read_null_terminated_string(cursor)?);
+        }
+    }
+
+    let export_start = export_table_idd.virtual_address;
+    let export_end = export_start.saturating_add(export_table_idd.size);
+
+    for (index, (rva, name)) in function_rvas.into_iter().zip(function_names).enumerate() {
+        /* Unused slots of the address table are zeroed out */
+        if rva == 0 {
+            continue;
+        }
+
+        let mut symbol = ExportedSymbol {
+            name,
+            ordinal: (directory.ordinal_base as usize + index) as u16,
+            rva,
+            forwarder: None,
+        };
+
+        if rva >= export_start
+            && rva < export_end
+            && let Some(forwarder_offset) = pe.convert_rva_to_file_offset(rva)
+        {
+            cursor.set_position(forwarder_offset);
+            symbol.forwarder = Some(read_null_terminated_string(cursor)?);
+        }
+
+        exports.add_symbol(symbol);
+    }
+
+    return Ok(Some(exports));
+}
+
+fn parse_debug_directories(
+    pe: &PE,
+    cursor: &mut io::Cursor<&[u8]>,
+) -> Result<Vec<ImageDebugDirectory>, PeError> {
+    let debug_idd = pe.get_debug_idd();
+
+    if debug_idd.virtual_address == 0 || debug_idd.size == 0 {
+        return Ok(Vec::new());
+    }
+
+    let file_offset = match pe.convert_rva_to_file_offset(debug_idd.virtual_address) {
+        Some(offset) => offset,
+        _ => {
+            return Ok(Vec::new());
+        }
+    };
+
+    cursor.set_position(file_offset);
+
+    let mut debug_directories: Vec<ImageDebugDirectory> = Vec::new();
+
+    for _ in 0..debug_idd.size / IMAGE_DEBUG_DIRECTORY_SIZE {
+        debug_directories.push(ImageDebugDirectory::from_parser(cursor)?);
+    }
+
+    return Ok(debug_directories);
+}
+
+fn parse_load_config(
+    pe: &PE,
+    cursor: &mut io::Cursor<&[u8]>,
+) -> Result<Option<LoadConfigDirectory>, PeError> {
+    let load_config_idd = pe.get_load_config_table_idd();
+
+    if load_config_idd.virtual_address == 0 || load_config_idd.size == 0 {
+        return Ok(None);
+    }
+
+    let file_offset = match pe.convert_rva_to_file_offset(load_config_idd.virtual_address) {
+        Some(offset) => offset,
+        _ => {
+            return Ok(None);
+        }
+    };
+
+    cursor.set_position(file_offset);
+
+    return Ok(Some(LoadConfigDirectory::from_parser(
+        cursor,
+        pe.is_32_bits(),
+    )?));
+}
+
+/*
+ * Parse the resource directory tree into the list of its leaves. Returns an empty vector if the PE
+ * has no resources
+ */
+fn parse_resources(pe: &PE, cursor: &mut io::Cursor<&[u8]>) -> Result<Vec<Resource>, PeError> {
+    let resource_table_idd = pe.get_resource_table_idd();
+
+    if resource_table_idd.virtual_address == 0 || resource_table_idd.size == 0 {
+        return Ok(Vec::new());
+    }
+
+    let directory_offset = match pe.convert_rva_to_file_offset(resource_table_idd.virtual_address) {
+        Some(offset) => offset,
+        _ => {
+            return Ok(Vec::new());
+        }
+    };
+
+    let mut resources: Vec<Resource> = Vec::new();
+
+    for type_entry in parse_resource_directory_entries(cursor, directory_offset, 0)? {
+        if !type_entry.is_directory() {
+            continue;
+        }
+
+        let resource_type = parse_resource_id(cursor, directory_offset, &type_entry)?;
+
+        for name_entry in parse_resource_directory_entries(
+            cursor,
+            directory_offset,
+            type_entry.get_data_offset(),
+        )? {
+            if !name_entry.is_directory() {
+                continue;
+            }
+
+            let name = parse_resource_id(cursor, directory_offset, &name_entry)?;
+
+            for language_entry in parse_resource_directory_entries(
+                cursor,
+                directory_offset,
+                name_entry.get_data_offset(),
+            )? {
+                if language_entry.is_directory() {
+                    continue;
+                }
+
+                cursor.set_position(directory_offset + language_entry.get_data_offset());
+
+                let data_entry = ImageResourceDataEntry::from_parser(cursor)?;
+
+                resources.push(Resource {
+                    resource_type: resource_type.clone(),
+                    name: name.clone(),
+                    language: language_entry.name_or_id as u16,
+                    data_rva: data_entry.data_rva,
+                    size: data_entry.size,
+                    code_page: data_entry.code_page,
+                });
+            }
+        }
+    }
+
+    return Ok(resources);
+}
+
+fn parse_resource_directory_entries(
+    cursor: &mut io::Cursor<&[u8]>,
+    directory_offset: u64,
+    table_offset: u64,
+) -> Result<Vec<ImageResourceDirectoryEntry>, PeError> {
+    cursor.set_position(directory_offset + table_offset);
+
+    let directory = ImageResourceDirectory::from_parser(cursor)?;
+
+    let mut entries: Vec<ImageResourceDirectoryEntry> = Vec::new();
+
+    for _ in 0..(directory.number_of_named_entries as u32 + directory.number_of_id_entries as u32) {
+        entries.push(ImageResourceDirectoryEntry::from_parser(cursor)?);
+    }
+
+    return Ok(entries);
+}
+
+/*
+ * Names of named entries are stored as a length followed by utf-16 characters, without terminator
+ */
+fn parse_resource_id(
+    cursor: &mut io::Cursor<&[u8]>,
+    directory_offset: u64,
+    entry: &ImageResourceDirectoryEntry,
+) -> Result<ResourceId, PeError> {
+    if !entry.is_named() {
+        return Ok(ResourceId::Id(entry.name_or_id as u16));
+    }
+
+    let name_position = directory_offset + entry.get_name_offset();
+
+    cursor.set_position(name_position);
+
+    let length = cursor.read_word()?;
+    let mut name: Vec<u16> = Vec::new();
+
+    for _ in 0..length {
+        name.push(cursor.read_word()?);
+    }
+
+    return String::from_utf16(&name)
+        .map(ResourceId::Name)
+        .map_err(|_| PeError::InvalidName {
+            offset: name_position,
+        });
+}
+
+/*
+ * Reads an utf-16 null-terminated string starting at the current cursor position, stopping at the
+ * given end position if there is no terminator
+ */
+fn read_utf16_null_terminated_string(
+    cursor: &mut io::Cursor<&[u8]>,
+    end: u64,
+) -> Result<String, PeError> {
+    let name_position = cursor.position();
+    let mut name_buffer: Vec<u16> = Vec::new();
+
+    while cursor.position() + 1 < end {
+        let c = cursor.read_word()?;
+
+        if c == 0x0 {
+            break;
+        }
+
+        name_buffer.push(c);
+    }
+
+    return String::from_utf16(&name_buffer).map_err(|_| PeError::InvalidName {
+        offset: name_position,
+    });
+}
+
+/*
+ * Reads an ASCII null-terminated string starting at the current cursor position
+ */
+fn read_null_terminated_string(cursor: &mut io::Cursor<&[u8]>) -> Result<String, PeError> {
+    let name_position = cursor.position();
+    let mut name_buffer: Vec<u8> = Vec::new();
+
+    loop {
+        let c = cursor.read_byte()?;
+
+        if c == 0x0 {
+            break;
+        }
+
+        name_buffer.push(c);
+    }
+
+    return String::from_utf8(name_buffer).map_err(|_| PeError::InvalidName {
+        offset: name_position,
+    });
+}
+
+/*
+ * Extensions commonly used by Portable Executables. Detection relies on the DOS magic number and
+ * the PE signature, this list is only used when the caller wants to check the extension too
+ */
+pub const PE_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "sys", "drv", "ocx", "cpl", "scr", "ax", "acm", "pyd", "node", "mui", "efi",
+    "tsp", "winmd",
+];
+
+pub fn has_pe_extension(file_path: &Path) -> bool {
+    return match file_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+    {
+        Some(extension) => PE_EXTENSIONS
+            .iter()
+            .any(|pe_extension| extension.eq_ignore_ascii_case(pe_extension)),
+        None => false,
+    };
+}
+
+/*
+ * Main parse method that reads from a file, tests if it's a PE file or not (from the DOS magic
+ * number and the PE signature), and returns the parsed PE
+ */
+pub fn parse_pe(file_path: &Path) -> Result<PE, PeError> {
+    let data = PEData::read(file_path).map_err(|err| PeError::Io {
+        path: file_path.to_path_buf(),
+        source: err,
+    })?;
+
+    return parse_pe_data(data);
+}
+
+/*
+ * Parses a PE from its raw bytes
+ */
+pub fn parse_pe_data(data: PEData) -> Result<PE, PeError> {
+    let mut pe: PE = PE::new();
+    pe.data = data;
+
+    let mut cursor = io::Cursor::new(&pe.data[..]);
+
+    let dos_header = DOSHeader::from_parser(&mut cursor)?;
+
+    cursor.set_position(dos_header.lfanew as u64);
+
+    let nt_header = NTHeader::from_parser(&mut cursor)?;
+
+    let start_of_optional_position = cursor.position();
+
+    let optional_magic: u16 = cursor.read_word()?;
+    cursor.set_position(start_of_optional_position);
+
+    match optional_magic {
+        PE_FORMAT_32_MAGIC => {
+            let optional_header: OptionalHeader32 = OptionalHeader32::from_parser(&mut cursor)?;
+
+            pe.header = PEHeader::PE32(PE32Header {
+                dos: dos_header,
+                nt: nt_header,
+                optional: optional_header,
+            });
+        }
+        PE_FORMAT_64_MAGIC => {
+            let optional_header: OptionalHeader64 = OptionalHeader64::from_parser(&mut cursor)?;
+
+            pe.header = PEHeader::PE64(PE64Header {
+                dos: dos_header,
+                nt: nt_header,
+                optional: optional_header,
+            });
+        }
+        _ => {
+            return Err(PeError::BadMagic {
+                offset: start_of_optional_position,
+                what: "PE optional header magic",
+                found: optional_magic as u32,
+            });
+        }
+    }
+
+    /* Section headers follow the optional header, whatever the number of data directories */
+    cursor.set_position(start_of_optional_position + pe.get_size_of_optional_header());
+
+    let string_table_offset = pe.get_string_table_offset();
+
+    for _ in 0..pe.get_number_of_sections() {
+        let section_header = SectionHeader::from_parser(&mut cursor, string_table_offset)?;
+
+        pe.sections.insert(
+            section_header.name.clone(),
+            Section {
+                header: section_header,
+            },
+        );
+    }
+
+    return Ok(pe);
+}
+
+/*
+ * PE32 and PE32+ images built in memory for the tests, with a single section holding the data of
+ * the directories
+ */
+#[cfg(test)]
+pub mod test_image {
+    use super::{IMAGE_FILE_MACHINE_ARMNT, IMAGE_FILE_MACHINE_I386, ImportedSymbol};
+
+    pub const EXPORT_DIRECTORY: usize = 0;
+    pub const IMPORT_DIRECTORY: usize = 1;
+    pub const RESOURCE_DIRECTORY: usize = 2;
+    pub const DEBUG_DIRECTORY: usize = 6;
+    pub const LOAD_CONFIG_DIRECTORY: usize = 10;
+    pub const DELAY_IMPORT_DIRECTORY: usize = 13;
+
+    const NT_HEADER_OFFSET: usize = 0x40;
+    const SECTION_RVA: u32 = 0x1000;
+    const SECTION_FILE_OFFSET: usize = 0x200;
+
+    /*
+     * Image with a single section holding every table. x86 and ARM images are PE32, the others
+     * PE32+
+     */
+    pub struct ImageBuilder {
+        machine: u16,
+        characteristics: u16,
+        subsystem: u16,
+        dll_characteristics: u16,
+        image_base: u64,
+        directories: [(u32, u32); 16],
+        section_name: &'static str,
+        section: Vec<u8>,
+    }
+
+    impl ImageBuilder {
+        pub fn new(machine: u16) -> ImageBuilder {
+            return ImageBuilder {
+                machine,
+                characteristics: 0x2022, /* EXECUTABLE_IMAGE | LARGE_ADDRESS_AWARE | DLL */
+                subsystem: 0,
+                dll_characteristics: 0,
+                image_base: 0,
+                directories: [(0, 0); 16],
+                section_name: ".data",
+                section: Vec::new(),
+            };
+        }
+
+        pub fn is_32_bits(&self) -> bool {
+            return matches!(
+                self.machine,
+                IMAGE_FILE_MACHINE_I386 | IMAGE_FILE_MACHINE_ARMNT
+            );
+        }
+
+        /* Appends data to the section, returning its RVA */
+        pub fn append(&mut self, data: &[u8]) -> u32 {
+            let rva = SECTION_RVA + self.section.len() as u32;
+            self.section.extend_from_slice(data);
+
+            return rva;
+        }
+
+        pub fn set_directory(&mut self, index: usize, rva: u32, size: u32) {
+            self.directories[index] = (rva, size);
+        }
+
+        pub fn set_characteristics(&mut self, characteristics: u16) {
+            self.characteristics = characteristics;
+        }
+
+        pub fn set_subsystem(&mut self, subsystem: u16) {
+            self.subsystem = subsystem;
+        }
+
+        pub fn set_dll_characteristics(&mut self, dll_characteristics: u16) {
+            self.dll_characteristics = dll_characteristics;
+        }
+
+        pub fn set_image_base(&mut self, image_base: u64) {
+            self.image_base = image_base;
+        }
+
+        /* Names longer than 8 bytes are truncated, as in the section header */
+        pub fn set_section_name(&mut self, section_name: &'static str) {
+            self.section_name = section_name;
+        }
+
+        /* Debug directory holding a single entry, pointing to its data */
+        pub fn add_debug_entry(&mut self, debug_type: u32, data: &[u8]) {
+            let data_rva = self.append(data);
+            let file_offset = SECTION_FILE_OFFSET as u32 + data_rva - SECTION_RVA;
+
+            let mut entry: Vec<u8> = vec![0u8; 12];
+
+            for dword in [debug_type, data.len() as u32, data_rva, file_offset] {
+                entry.extend_from_slice(&dword.to_le_bytes());
+            }
+
+            let rva = self.append(&entry);
+            self.set_directory(DEBUG_DIRECTORY, rva, entry.len() as u32);
+        }
+
+        /*
+         * Resource tree of (type, id, data) resources, all in the neutral language
+         */
+        pub fn add_resources(&mut self, resources: &[(u16, u16, &[u8])]) {
+            let mut types: Vec<(u16, Vec<usize>)> = Vec::new();
+
+            for (index, (resource_type, _, _)) in resources.iter().enumerate() {
+                match types.iter_mut().find(|(other, _)| other == resource_type) {
+                    Some((_, indexes)) => indexes.push(index),
+                    None => types.push((*resource_type, vec![index])),
+                }
+            }
+
+            /* Offsets of the type directories, then of the name directories holding the languages */
+            let mut offset = 16 + 8 * types.len() as u32;
+            let mut type_offsets: Vec<u32> = Vec::new();
+
+            for (_, indexes) in &types {
+                type_offsets.push(offset);
+                offset += 16 + 8 * indexes.len() as u32;
+            }
+
+            let names_offset = offset;
+            let data_entries_offset = names_offset + 24 * resources.len() as u32;
+            let mut data_offset = data_entries_offset + 16 * resources.len() as u32;
+
+            let directory = |entries: &[(u32, u32)]| -> Vec<u8> {
+                let mut directory = vec![0u8; 12];
+                directory.extend_from_slice(&0u16.to_le_bytes());
+                directory.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+
+                for (id, offset) in entries {
+                    directory.extend_from_slice(&id.to_le_bytes());
+                    directory.extend_from_slice(&offset.to_le_bytes());
+                }
+
+                return directory;
+            };
+
+            let mut tree: Vec<u8> = directory(
+                &types
+                    .iter()
+                    .zip(&type_offsets)
+                    .map(|((resource_type, _), offset)| {
+                        (*resource_type as u32, offset | 0x80000000)
+                    })
+                    .collect::<Vec<(u32, u32)>>(),
+            );
+
+            for (_, indexes) in &types {
+                tree.extend(directory(
+                    &indexes
+                        .iter()
+                        .map(|index| {
+                            (
+                                resources[*index].1 as u32,
+                                (names_offset + 24 * *index as u32) | 0x80000000,
+                            )
+                        })
+                        .collect::<Vec<(u32, u32)>>(),
+                ));
+            }
+
+            for index in 0..resources.len() as u32 {
+                tree.extend(directory(&[(0, data_entries_offset + 16 * index)]));
+            }
+
+            let start = SECTION_RVA + self.section.len() as u32;
+
+            for (_, _, data) in resources {
+                for dword in [start + data_offset, data.len() as u32, 0, 0] {
+                    tree.extend_from_slice(&dword.to_le_bytes());
+                }
+
+                data_offset += data.len() as u32;
+            }
+
+            for (_, _, data) in resources {
+                tree.extend_from_slice(data);
+            }
+
+            self.append(&tree);
+            self.set_directory(RESOURCE_DIRECTORY, start, tree.len() as u32);
+        }
+
+        /*
+         * Name, Hint/Name Table entries and lookup table of an imported dll, then its import address
+         * table, a copy of the lookup table. Addresses are shifted by the bias, the image base for
+         * VA-based tables. Returns the addresses of the name, the lookup table and the IAT
+         */
+        fn add_imported_dll(
+            &mut self,
+            dll_name: &str,
+            symbols: &[ImportedSymbol],
+            bias: u32,
+        ) -> [u32; 3] {
+            let name_rva = self.append(format!("{dll_name}\0").as_bytes());
+
+            let mut lookup_table: Vec<u8> = Vec::new();
+
+            for symbol in symbols {
+                let entry: u64 = match symbol {
+                    ImportedSymbol::ByName { hint, name } => {
+                        let mut hint_name = hint.to_le_bytes().to_vec();
+                        hint_name.extend_from_slice(name.as_bytes());
+                        hint_name.resize(hint_name.len() + 2 - hint_name.len() % 2, 0);
+
+                        (self.append(&hint_name) + bias) as u64
+                    }
+                    ImportedSymbol::ByOrdinal(ordinal) => match self.is_32_bits() {
+                        true => 0x80000000 | *ordinal as u64,
+                        false => 0x8000000000000000 | *ordinal as u64,
+                    },
+                };
+
+                match self.is_32_bits() {
+                    true => lookup_table.extend_from_slice(&(entry as u32).to_le_bytes()),
+                    false => lookup_table.extend_from_slice(&entry.to_le_bytes()),
+                }
+            }
+
+            lookup_table.resize(
+                lookup_table.len() + if self.is_32_bits() { 4 } else { 8 },
+                0,
+            );
+
+            let lookup_table_rva = self.append(&lookup_table);
+            let iat_rva = self.append(&lookup_table);
+
+            return [name_rva + bias, lookup_table_rva + bias, iat_rva + bias];
+        }
+
+        /*
+         * Import table of (dll name, symbols) imports
+         */
+        pub fn add_imports(&mut self, dlls: &[(&str, &[ImportedSymbol])]) {
+            let mut descriptors: Vec<u8> = Vec::new();
+
+            for (dll_name, symbols) in dlls {
+                let [name_rva, lookup_table_rva, iat_rva] =
+                    self.add_imported_dll(dll_name, symbols, 0);
+
+                for dword in [lookup_table_rva, 0, 0, name_rva, iat_rva] {
+                    descriptors.extend_from_slice(&dword.to_le_bytes());
+                }
+            }
+
+            descriptors.extend_from_slice(&[0; 20]);
+
+            let rva = self.append(&descriptors);
+            self.set_directory(IMPORT_DIRECTORY, rva, descriptors.len() as u32);
+        }
+
+        /*
+         * Delay-load import table of (dll name, symbols) imports. Descriptors that are not RVA
+         * based, as emitted by old linkers, hold VAs computed from the image base
+         */
+        pub fn add_delay_imports(&mut self, dlls: &[(&str, &[ImportedSymbol])], rva_based: bool) {
+            let bias = match rva_based {
+                true => 0,
+                false => self.image_base as u32,
+            };
+
+            let mut descriptors: Vec<u8> = Vec::new();
+
+            for (dll_name, symbols) in dlls {
+                let module_handle_rva = self.append(&[0; 8]) + bias;
+                let [name_rva, lookup_table_rva, iat_rva] =
+                    self.add_imported_dll(dll_name, symbols, bias);
+
+                for dword in [
+                    rva_based as u32,
+                    name_rva,
+                    module_handle_rva,
+                    iat_rva,
+                    lookup_table_rva,
+                    0,
+                    0,
+                    0,
+                ] {
+                    descriptors.extend_from_slice(&dword.to_le_bytes());
+                }
+            }
+
+            descriptors.extend_from_slice(&[0; 32]);
+
+            let rva = self.append(&descriptors);
+            self.set_directory(DELAY_IMPORT_DIRECTORY, rva, descriptors.len() as u32);
+        }
+
+        /*
+         * Export table of (name, forwarder) symbols, their ordinals starting at 1
+         */
+        pub fn add_exports(&mut self, dll_name: &str, symbols: &[(&str, Option<&str>)]) {
+            let count = symbols.len() as u32;
+            let start = SECTION_RVA + self.section.len() as u32;
+
+            let functions_rva = start + 40;
+            let names_rva = functions_rva + count * 4;
+            let ordinals_rva = names_rva + count * 4;
+
+            let mut strings: Vec<u8> = Vec::new();
+            let strings_rva = ordinals_rva + count * 2;
+
+            let mut add_string = |string: &str| -> u32 {
+                let rva = strings_rva + strings.len() as u32;
+                strings.extend_from_slice(string.as_bytes());
+                strings.push(0);
+
+                return rva;
+            };
+
+            let dll_name_rva = add_string(dll_name);
+
+            let mut functions: Vec<u8> = Vec::new();
+            let mut names: Vec<u8> = Vec::new();
+            let mut ordinals: Vec<u8> = Vec::new();
+
+            for (index, (name, forwarder)) in symbols.iter().enumerate() {
+                let function_rva = match forwarder {
+                    Some(forwarder) => add_string(forwarder),
+                    None => 0x8000 + index as u32 * 0x10,
+                };
+
+                functions.extend_from_slice(&function_rva.to_le_bytes());
+                names.extend_from_slice(&add_string(name).to_le_bytes());
+                ordinals.extend_from_slice(&(index as u16).to_le_bytes());
+            }
+
+            let mut directory: Vec<u8> = Vec::new();
+
+            for dword in [0, 0, 0, dll_name_rva, 1, count, count] {
+                directory.extend_from_slice(&dword.to_le_bytes());
+            }
+
+            for rva in [functions_rva, names_rva, ordinals_rva] {
+                directory.extend_from_slice(&rva.to_le_bytes());
+            }
+
+            for data in [directory, functions, names, ordinals, strings] {
+                self.append(&data);
+            }
+
+            let size = SECTION_RVA + self.section.len() as u32 - start;
+            self.set_directory(EXPORT_DIRECTORY, start, size);
+        }
+
+        pub fn build(&self) -> Vec<u8> {
+            let mut image = vec![0u8; SECTION_FILE_OFFSET];
+
+            image[0..2].copy_from_slice(b"MZ");
+            image[0x3c..0x40].copy_from_slice(&(NT_HEADER_OFFSET as u32).to_le_bytes());
+
+            let mut headers: Vec<u8> = Vec::new();
+
+            /* The data directories follow the fields of the optional header */
+            let (magic, directories_offset) = match self.is_32_bits() {
+                true => (0x10bu16, 96),
+                false => (0x20bu16, 112),
+            };
+
+            let optional_header_size = directories_offset + 16 * 8;
+
+            /* Signature and COFF header */
+            headers.extend_from_slice(b"PE\0\0");
+            headers.extend_from_slice(&self.machine.to_le_bytes());
+            headers.extend_from_slice(&1u16.to_le_bytes());
+            headers.extend_from_slice(&[0; 12]);
+            headers.extend_from_slice(&(optional_header_size as u16).to_le_bytes());
+            headers.extend_from_slice(&self.characteristics.to_le_bytes());
+
+            /*
+             * Optional header, only the magic, the image base, the subsystem, the DLL
+             * characteristics and the data directories are set
+             */
+            let mut optional_header = vec![0u8; optional_header_size];
+            optional_header[0..2].copy_from_slice(&magic.to_le_bytes());
+            optional_header[68..70].copy_from_slice(&self.subsystem.to_le_bytes());
+            optional_header[70..72].copy_from_slice(&self.dll_characteristics.to_le_bytes());
+
+            match self.is_32_bits() {
+                true => {
+                    optional_header[28..32].copy_from_slice(&(self.image_base as u32).to_le_bytes())
+                }
+                false => optional_header[24..32].copy_from_slice(&self.image_base.to_le_bytes()),
+            }
+            optional_header[directories_offset - 4..directories_offset]
+                .copy_from_slice(&16u32.to_le_bytes());
+
+            for (index, (rva, size)) in self.directories.iter().enumerate() {
+                let offset = directories_offset + index * 8;
+                optional_header[offset..offset + 4].copy_from_slice(&rva.to_le_bytes());
+                optional_header[offset + 4..offset + 8].copy_from_slice(&size.to_le_bytes());
+            }
+
+            headers.extend_from_slice(&optional_header);
+
+            /* Section header */
+            let section_size = self.section.len() as u32;
+
+            let mut section_name = [0u8; 8];
+            let name_length = self.section_name.len().min(8);
+            section_name[..name_length]
+                .copy_from_slice(&self.section_name.as_bytes()[..name_length]);
+
+            headers.extend_from_slice(&section_name);
+
+            for dword in [
+                section_size,
+                SECTION_RVA,
+                section_size,
+                SECTION_FILE_OFFSET as u32,
+            ] {
+                headers.extend_from_slice(&dword.to_le_bytes());
+            }
+
+            headers.extend_from_slice(&[0; 16]);
+
+            image[NT_HEADER_OFFSET..NT_HEADER_OFFSET + headers.len()].copy_from_slice(&headers);
+            image.extend_from_slice(&self.section);
+
+            return image;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_directory::TestDirectory;
+    use super::test_image::*;
+    use super::*;
+
+    fn exported_symbol(name: Option<&str>, ordinal: u16, rva: u32) -> ExportedSymbol {
+        return ExportedSymbol {
+            name: name.map(str::to_string),
+            ordinal,
+            rva,
+            forwarder: None,
+        };
+    }
+
+    #[test]
+    fn export_table_finds_symbols_by_name_and_ordinal() {
+        let mut exports = ExportTable::new("a.dll".to_string(), 1);
+
+        exports.add_symbol(exported_symbol(Some("Foo"), 1, 0x1000));
+        exports.add_symbol(exported_symbol(None, 2, 0x2000));
+        exports.add_symbol(exported_symbol(Some("Bar"), 3, 0x3000));
+
+        assert_eq!(exports.find_by_name("Bar").map(|s| s.rva), Some(0x3000));
+        assert_eq!(exports.find_by_ordinal(2).map(|s| s.rva), Some(0x2000));
+        assert!(exports.find_by_name("foo").is_none());
+        assert!(exports.find_by_ordinal(4).is_none());
+
+        let by_name = ImportedSymbol::ByName {
+            hint: 0,
+            name: "Foo".to_string(),
+        };
+
+        assert_eq!(exports.find(&by_name).map(|s| s.ordinal), Some(1));
+        assert_eq!(
+            exports.find(&ImportedSymbol::ByOrdinal(3)).map(|s| s.rva),
+            Some(0x3000)
+        );
+    }
+
+    #[test]
+    fn string_table_offsets_are_decimal_or_base64() {
+        assert_eq!(parse_string_table_offset(b"4"), Some(4));
+        assert_eq!(parse_string_table_offset(b"1234567"), Some(1234567));
+        assert_eq!(parse_string_table_offset(b"/AAAAAE"), Some(4));
+        assert_eq!(parse_string_table_offset(b"/AAmJaA"), Some(10_000_000));
+        assert_eq!(parse_string_table_offset(b"/A//"), Some(4095));
+        assert_eq!(parse_string_table_offset(b"12a"), None);
+        assert_eq!(parse_string_table_offset(b"/AA-A"), None);
+        assert_eq!(parse_string_table_offset(b"/"), None);
+    }
+
+    #[test]
+    fn export_table_keeps_the_first_symbol_of_a_name() {
+        let mut exports = ExportTable::new("a.dll".to_string(), 1);
+
+        exports.add_symbol(exported_symbol(Some("Foo"), 1, 0x1000));
+        exports.add_symbol(exported_symbol(Some("Foo"), 2, 0x2000));
+
+        assert_eq!(exports.find_by_name("Foo").map(|s| s.rva), Some(0x1000));
+        assert_eq!(exports.get_symbols().len(), 2);
+    }
+
+    #[test]
+    fn forwarders_name_their_module_and_symbol() {
+        let (module, symbol) = ImportedSymbol::from_forwarder("NTDLL.RtlAllocateHeap").unwrap();
+
+        assert_eq!(module, "NTDLL");
+        assert!(matches!(symbol, ImportedSymbol::ByName { name, .. } if name == "RtlAllocateHeap"));
+
+        let (module, symbol) = ImportedSymbol::from_forwarder("NTDLL.#12").unwrap();
+
+        assert_eq!(module, "NTDLL");
+        assert!(matches!(symbol, ImportedSymbol::ByOrdinal(12)));
+
+        /* Only the last dot separates the symbol, module names can hold dots */
+        let (module, symbol) =
+            ImportedSymbol::from_forwarder("api-ms-win-core-com-l1-1-0.dll.CoCreateGuid").unwrap();
+
+        assert_eq!(module, "api-ms-win-core-com-l1-1-0.dll");
+        assert!(matches!(symbol, ImportedSymbol::ByName { name, .. } if name == "CoCreateGuid"));
+
+        assert!(ImportedSymbol::from_forwarder("RtlAllocateHeap").is_none());
+        assert!(ImportedSymbol::from_forwarder("NTDLL.#x12").is_none());
+        assert!(ImportedSymbol::from_forwarder("NTDLL.#70000").is_none());
+    }
+
+    #[test]
+    fn exports_are_parsed_with_their_forwarders() {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.add_exports(
+            "a.dll",
+            &[("Foo", None), ("Bar", Some("NTDLL.RtlAllocateHeap"))],
+        );
+
+        let pe = parse_pe_data(PEData::Owned(image.build())).unwrap();
+        let exports = pe.get_exports().unwrap();
+
+        assert_eq!(exports.dll_name, "a.dll");
+        assert_eq!(exports.get_symbols().len(), 2);
+
+        let foo = exports.find_by_name("Foo").unwrap();
+
+        assert_eq!(foo.ordinal, 1);
+        assert!(!foo.is_forwarder());
+
+        let bar = exports.find_by_ordinal(2).unwrap();
+
+        assert_eq!(bar.name.as_deref(), Some("Bar"));
+        assert_eq!(bar.forwarder.as_deref(), Some("NTDLL.RtlAllocateHeap"));
+    }
+
+    /* Image with a 64-bit load config of the given size, holding a CHPE metadata pointer */
+    fn build_image_with_load_config(machine: u16, size: u32, chpe_metadata_pointer: u64) -> PE {
+        let mut load_config = vec![0u8; 0xd0];
+        load_config[0..4].copy_from_slice(&size.to_le_bytes());
+        load_config[0xc8..0xd0].copy_from_slice(&chpe_metadata_pointer.to_le_bytes());
+
+        let mut image = ImageBuilder::new(machine);
+        let rva = image.append(&load_config);
+        image.set_directory(LOAD_CONFIG_DIRECTORY, rva, size);
+
+        return parse_pe_data(PEData::Owned(image.build())).unwrap();
+    }
+
+    #[test]
+    fn hybrid_images_are_detected_from_their_chpe_metadata() {
+        let pe = build_image_with_load_config(IMAGE_FILE_MACHINE_AMD64, 0xd0, 0x180012000);
+
+        assert_eq!(pe.get_machine(), Machine::Arm64Ec);
+
+        let pe = build_image_with_load_config(IMAGE_FILE_MACHINE_ARM64, 0xd0, 0x180012000);
+
+        assert_eq!(pe.get_machine(), Machine::Arm64X);
+
+        let pe = build_image_with_load_config(IMAGE_FILE_MACHINE_AMD64, 0xd0, 0);
+
+        assert_eq!(pe.get_machine(), Machine::Amd64);
+
+        /* Load configs of older linkers end before the CHPE metadata pointer */
+        let pe = build_image_with_load_config(IMAGE_FILE_MACHINE_ARM64, 0xc8, 0x180012000);
+
+        assert_eq!(pe.get_machine(), Machine::Arm64);
+
+        let pe = parse_pe_data(PEData::Owned(
+            ImageBuilder::new(IMAGE_FILE_MACHINE_ARM64).build(),
+        ))
+        .unwrap();
+
+        assert_eq!(pe.get_machine(), Machine::Arm64);
+    }
+
+    fn by_name(hint: u16, name: &str) -> ImportedSymbol {
+        return ImportedSymbol::ByName {
+            hint,
+            name: name.to_string(),
+        };
+    }
+
+    /* (name, delay_load, symbols) of each imported dll */
+    fn describe_imports(pe: &PE) -> Vec<(String, bool, Vec<String>)> {
+        return pe
+            .get_imported_dlls()
+            .unwrap()
+            .iter()
+            .map(|dll| {
+                (
+                    dll.name.clone(),
+                    dll.delay_load,
+                    dll.symbols
+                        .iter()
+                        .map(|symbol| symbol.to_string())
+                        .collect(),
+                )
+            })
+            .collect();
+    }
+
+    fn build_image_with_delay_imports(machine: u16, image_base: u64, rva_based: bool) -> PE {
+        let mut image = ImageBuilder::new(machine);
+        image.set_image_base(image_base);
+        image.add_imports(&[("kernel32.dll", &[by_name(0x2b5, "GetProcAddress")])]);
+        image.add_delay_imports(
+            &[
+                (
+                    "user32.dll",
+                    &[by_name(0x28c, "MessageBoxW"), ImportedSymbol::ByOrdinal(12)],
+                ),
+                ("shell32.dll", &[by_name(0, "ShellExecuteW")]),
+            ],
+            rva_based,
+        );
+
+        return parse_pe_data(PEData::Owned(image.build())).unwrap();
+    }
+
+    fn expected_delay_imports() -> Vec<(String, bool, Vec<String>)> {
+        return vec![
+            (
+                "kernel32.dll".to_string(),
+                false,
+                vec!["GetProcAddress".to_string()],
+            ),
+            (
+                "user32.dll".to_string(),
+                true,
+                vec!["MessageBoxW".to_string(), "#12".to_string()],
+            ),
+            (
+                "shell32.dll".to_string(),
+                true,
+                vec!["ShellExecuteW".to_string()],
+            ),
+        ];
+    }
+
+    #[test]
+    fn delay_imports_are_parsed_from_rva_based_descriptors() {
+        let pe = build_image_with_delay_imports(IMAGE_FILE_MACHINE_AMD64, 0x180000000, true);
+
+        assert_eq!(describe_imports(&pe), expected_delay_imports());
+        assert!(matches!(
+            &pe.get_delay_imports()[0].symbols[0],
+            ImportedSymbol::ByName { hint: 0x28c, .. }
+        ));
+    }
+
+    #[test]
+    fn delay_imports_are_parsed_from_va_based_descriptors() {
+        /* Descriptors of old linkers, with the RVA based attribute clear */
+        let pe = build_image_with_delay_imports(IMAGE_FILE_MACHINE_I386, 0x400000, false);
+
+        assert_eq!(describe_imports(&pe), expected_delay_imports());
+        assert!(matches!(
+            &pe.get_delay_imports()[0].symbols[0],
+            ImportedSymbol::ByName { hint: 0x28c, .. }
+        ));
+
+        let pe = build_image_with_delay_imports(IMAGE_FILE_MACHINE_I386, 0x400000, true);
+
+        assert_eq!(describe_imports(&pe), expected_delay_imports());
+    }
+
+    #[test]
+    fn truncated_dos_headers_are_reported() {
+        let err = parse_pe_data(PEData::Owned(b"M".to_vec())).unwrap_err();
+
+        assert!(matches!(err, PeError::Truncated { offset: 0 }), "{err:?}");
+
+        /* The magic is there, e_lfanew is not */
+        let err = parse_pe_data(PEData::Owned(b"MZ\x90\x00".to_vec())).unwrap_err();
+
+        assert!(
+            matches!(err, PeError::Truncated { offset: 0x3c }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn bad_dos_magic_is_reported() {
+        let mut data = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64).build();
+        data[0..2].copy_from_slice(b"ZM");
+
+        let err = parse_pe_data(PEData::Owned(data)).unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                PeError::BadMagic {
+                    offset: 0,
+                    found: 0x4d5a,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn bad_pe_signature_is_reported() {
+        let mut data = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64).build();
+        data[0x40..0x44].copy_from_slice(b"NE\0\0");
+
+        let err = parse_pe_data(PEData::Owned(data)).unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                PeError::BadMagic {
+                    offset: 0x40,
+                    what: "PE signature in NT Header",
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn out_of_range_nt_header_offset_is_reported() {
+        let mut data = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64).build();
+        data[0x3c..0x40].copy_from_slice(&0x10000u32.to_le_bytes());
+
+        let err = parse_pe_data(PEData::Owned(data)).unwrap_err();
+
+        assert!(
+            matches!(err, PeError::Truncated { offset: 0x10000 }),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn import_table_outside_of_the_sections_is_reported() {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.add_imports(&[("kernel32.dll", &[by_name(0, "GetProcAddress")])]);
+        image.set_directory(IMPORT_DIRECTORY, 0x9000, 40);
+
+        let pe = parse_pe_data(PEData::Owned(image.build())).unwrap();
+        let err = pe.get_imported_dlls().unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                PeError::RvaOutOfRange {
+                    rva: 0x9000,
+                    what: "Import Table"
+                }
+            ),
+            "{err:?}"
+        );
+
+        /* Without an import table, there is nothing to report */
+        let pe = parse_pe_data(PEData::Owned(
+            ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64).build(),
+        ))
+        .unwrap();
+
+        assert!(pe.get_imported_dlls().unwrap().is_empty());
+    }
+
+    #[test]
+    fn pe_files_are_detected_from_their_content() {
+        let directory = TestDirectory::new("pe-content");
+        let image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64).build();
+
+        /* Extensionless binaries and Python extension modules are PE files */
+        for file_name in ["python-ext", "_ctypes.cp312-win_amd64.pyd"] {
+            let file_path = directory.write(file_name, &image);
+
+            assert!(parse_pe(&file_path).is_ok(), "{file_name}");
+        }
+
+        let file_path = directory.write("fake.dll", "not a PE file");
+        let err = parse_pe(&file_path).unwrap_err();
+
+        assert!(has_pe_extension(&file_path));
+        assert!(
+            matches!(
+                err,
+                PeError::BadMagic {
+                    what: "DOS magic number",
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[test]
+    fn pe_extensions_are_matched_case_insensitively() {
+        for file_name in ["a.dll", "A.EXE", "b.Pyd", "c.sys", "d.winmd"] {
+            assert!(has_pe_extension(Path::new(file_name)), "{file_name}");
+        }
+
+        for file_name in ["notes.txt", "a.dll.bak", "python-ext", "dll"] {
+            assert!(!has_pe_extension(Path::new(file_name)), "{file_name}");
+        }
+    }
+
+    /*
+     * Version block with its value and children, the value length being given in utf-16
+     * characters for text values
+     */
+    fn version_block(key: &str, value: &[u8], text: bool, children: &[Vec<u8>]) -> Vec<u8> {
+        let value_length = match text {
+            true => value.len() / 2,
+            false => value.len(),
+        };
+
+        let mut block: Vec<u8> = vec![0, 0];
+        block.extend_from_slice(&(value_length as u16).to_le_bytes());
+        block.extend_from_slice(&(text as u16).to_le_bytes());
+        block.extend(format!("{key}\0").encode_utf16().flat_map(u16::to_le_bytes));
+        block.resize(block.len().next_multiple_of(4), 0);
+        block.extend_from_slice(value);
+
+        for child in children {
+            block.resize(block.len().next_multiple_of(4), 0);
+            block.extend_from_slice(child);
+        }
+
+        let length = block.len() as u16;
+        block[0..2].copy_from_slice(&length.to_le_bytes());
+
+        return block;
+    }
+
+    fn version_string(key: &str, value: &str) -> Vec<u8> {
+        let value: Vec<u8> = format!("{value}\0")
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+
+        return version_block(key, &value, true, &[]);
+    }
+
+    #[test]
+    fn version_resources_are_decoded() {
+        let mut fixed_file_info: Vec<u8> = Vec::new();
+
+        for dword in [
+            0xfeef04bdu32,
+            0x10000,
+            10 << 16,
+            19041 << 16 | 3636,
+            10 << 16,
+            19041 << 16,
+            0x3f,
+            0x1, /* VS_FF_DEBUG */
+            0x40004,
+            0x2, /* VFT_DLL */
+            0,
+            0,
+            0,
+        ] {
+            fixed_file_info.extend_from_slice(&dword.to_le_bytes());
+        }
+
+        let string_file_info = version_block(
+            "StringFileInfo",
+            &[],
+            true,
+            &[
+                version_block(
+                    "040904b0",
+                    &[],
+                    true,
+                    &[
+                        version_string("CompanyName", "Contoso Ltd."),
+                        version_string("FileVersion", "10.0.19041.3636 (WinBuild.160101.0800)"),
+                        version_string("ProductName", "Contoso"),
+                    ],
+                ),
+                /* Values of other languages do not replace the first ones */
+                version_block(
+                    "040c04b0",
+                    &[],
+                    true,
+                    &[
+                        version_string("CompanyName", "Contoso SARL"),
+                        version_string("Comments", "Version française"),
+                    ],
+                ),
+            ],
+        );
+
+        let var_file_info = version_block(
+            "VarFileInfo",
+            &[],
+            true,
+            &[version_block(
+                "Translation",
+                &[0x09, 0x04, 0xb0, 0x04],
+                false,
+                &[],
+            )],
+        );
+
+        let version_info = version_block(
+            "VS_VERSION_INFO",
+            &fixed_file_info,
+            false,
+            &[string_file_info, var_file_info],
+        );
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.add_resources(&[(RT_VERSION, 1, &version_info)]);
+
+        let pe = parse_pe_data(PEData::Owned(image.build())).unwrap();
+        let version_info = pe.get_version_info().unwrap();
+        let fixed_file_info = version_info.fixed_file_info.as_ref().unwrap();
+
+        assert_eq!(fixed_file_info.get_file_version(), "10.0.19041.3636");
+        assert_eq!(fixed_file_info.get_product_version(), "10.0.19041.0");
+        assert_eq!(fixed_file_info.get_file_flags(), 0x1);
+        assert_eq!(fixed_file_info.get_file_type(), 0x2);
+
+        assert_eq!(version_info.get_string("CompanyName"), Some("Contoso Ltd."));
+        assert_eq!(
+            version_info.get_string("FileVersion"),
+            Some("10.0.19041.3636 (WinBuild.160101.0800)")
+        );
+        assert_eq!(version_info.get_string("ProductName"), Some("Contoso"));
+        assert_eq!(
+            version_info.get_string("Comments"),
+            Some("Version française")
+        );
+        assert_eq!(version_info.get_string("Translation"), None);
+        assert_eq!(version_info.strings.len(), 4);
+    }
+
+    #[test]
+    fn unknown_machines_keep_their_value() {
+        for machine in [
+            IMAGE_FILE_MACHINE_I386,
+            IMAGE_FILE_MACHINE_AMD64,
+            IMAGE_FILE_MACHINE_ARM64,
+            0x1c0, /* ARM */
+        ] {
+            assert_eq!(Machine::from_u16(machine).to_u16(), machine);
+        }
+
+        assert_eq!(Machine::from_u16(0x1c0), Machine::Other(0x1c0));
+        assert_eq!(Machine::from_u16(0x1c0).get_name(), "unknown");
+        assert_eq!(Machine::from_u16(0x1c0).to_string(), "0x01c0");
+        assert_eq!(Machine::Amd64.to_string(), "x64");
+        assert_eq!(Machine::Arm64Ec.to_string(), "ARM64EC");
+        assert_eq!(Machine::Unknown.to_string(), "unknown");
+    }
+
+    #[test]
+    fn characteristics_flags_are_combined_and_named() {
+        let characteristics = Characteristics::EXECUTABLE_IMAGE | Characteristics::DLL;
+
+        assert!(characteristics.contains(Characteristics::DLL));
+        assert!(characteristics.contains(Characteristics::EXECUTABLE_IMAGE | Characteristics::DLL));
+        assert!(!characteristics.contains(Characteristics::DLL | Characteristics::SYSTEM));
+        assert_eq!(characteristics & Characteristics::DLL, Characteristics::DLL);
+        assert_eq!(characteristics.get_names(), ["EXECUTABLE_IMAGE", "DLL"]);
+        assert_eq!(
+            Characteristics::from_bits(0x0100).get_names(),
+            ["32BIT_MACHINE"]
+        );
+
+        let dll_characteristics = DllCharacteristics::from_bits(0x8160);
+
+        assert!(
+            dll_characteristics
+                .contains(DllCharacteristics::DYNAMIC_BASE | DllCharacteristics::NX_COMPAT)
+        );
+        assert!(!dll_characteristics.contains(DllCharacteristics::GUARD_CF));
+        assert_eq!(
+            dll_characteristics.get_names(),
+            [
+                "HIGH_ENTROPY_VA",
+                "DYNAMIC_BASE",
+                "NX_COMPAT",
+                "TERMINAL_SERVER_AWARE"
+            ]
+        );
+    }
+
+    #[test]
+    fn subsystems_are_named() {
+        assert_eq!(Subsystem::from_u16(2), Subsystem::WindowsGui);
+        assert_eq!(Subsystem::from_u16(3).to_string(), "windows_cui");
+        assert_eq!(
+            Subsystem::from_u16(16).to_string(),
+            "windows_boot_application"
+        );
+        assert_eq!(Subsystem::from_u16(4), Subsystem::Other(4));
+        assert_eq!(Subsystem::from_u16(4).to_string(), "unknown");
+    }
+
+    #[test]
+    fn header_fields_are_read_from_pe32_and_pe32_plus_images() {
+        for machine in [IMAGE_FILE_MACHINE_I386, IMAGE_FILE_MACHINE_AMD64] {
+            let mut image = ImageBuilder::new(machine);
+            image.set_characteristics(
+                (Characteristics::EXECUTABLE_IMAGE | Characteristics::DLL).bits(),
+            );
+            image.set_subsystem(3);
+            image.set_dll_characteristics(
+                (DllCharacteristics::DYNAMIC_BASE | DllCharacteristics::NX_COMPAT).bits(),
+            );
+
+            let pe = parse_pe_data(PEData::Owned(image.build())).unwrap();
+
+            assert_eq!(pe.get_machine(), Machine::from_u16(machine));
+            assert_eq!(pe.is_32_bits(), machine == IMAGE_FILE_MACHINE_I386);
+            assert!(pe.get_characteristics().contains(Characteristics::DLL));
+            assert_eq!(pe.get_subsystem(), Subsystem::WindowsCui);
+            assert_eq!(
+                pe.get_dll_characteristics(),
+                DllCharacteristics::DYNAMIC_BASE | DllCharacteristics::NX_COMPAT
+            );
+        }
+    }
+
+    #[test]
+    fn imports_are_parsed_by_name_and_ordinal() {
+        for machine in [IMAGE_FILE_MACHINE_I386, IMAGE_FILE_MACHINE_AMD64] {
+            let mut image = ImageBuilder::new(machine);
+            image.add_imports(&[
+                (
+                    "KERNEL32.dll",
+                    &[by_name(0x2b5, "GetProcAddress"), by_name(0x5a1, "Sleep")],
+                ),
+                (
+                    "WS2_32.dll",
+                    &[ImportedSymbol::ByOrdinal(115), ImportedSymbol::ByOrdinal(1)],
+                ),
+            ]);
+
+            let pe = parse_pe_data(PEData::Owned(image.build())).unwrap();
+
+            assert_eq!(
+                describe_imports(&pe),
+                [
+                    (
+                        "KERNEL32.dll".to_string(),
+                        false,
+                        vec!["GetProcAddress".to_string(), "Sleep".to_string()]
+                    ),
+                    (
+                        "WS2_32.dll".to_string(),
+                        false,
+                        vec!["#115".to_string(), "#1".to_string()]
+                    ),
+                ],
+                "{machine:#x}"
+            );
+
+            let kernel32 = &pe.get_imports().unwrap()[0];
+
+            assert!(matches!(
+                &kernel32.symbols[0],
+                ImportedSymbol::ByName { hint: 0x2b5, .. }
+            ));
+            assert!(matches!(
+                &kernel32.symbols[1],
+                ImportedSymbol::ByName { hint: 0x5a1, .. }
+            ));
+        }
+    }
 }
@@ -1,65 +1,982 @@
 use json;
 use log;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
-fn find_dll(name: &str, search_paths: &[PathBuf]) -> Result<String, Box<dyn std::error::Error>> {
-    log::trace!("find_dll(): Looking for dll: {name}");
+/*
+ * Dll search order
+ * https://learn.microsoft.com/en-us/windows/win32/dlls/dynamic-link-library-search-order
+ */
 
-    for path in search_paths.iter() {
-        let entries = match std::fs::read_dir(path) {
-            Ok(e) => e,
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchLocation {
+    ApplicationDirectory, /* Directory of the analyzed executable */
+    System32Directory,    /* <windows>/System32 */
+    SystemDirectory,      /* <windows>/System, the 16-bit system directory */
+    WindowsDirectory,     /* <windows> */
+    CurrentDirectory,
+    PathDirectories,
+    UserDirectories, /* --search-paths */
+}
+
+impl SearchLocation {
+    pub fn from_name(name: &str) -> Option<SearchLocation> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "app" => return Some(SearchLocation::ApplicationDirectory),
+            "system32" => return Some(SearchLocation::System32Directory),
+            "system" => return Some(SearchLocation::SystemDirectory),
+            "windows" => return Some(SearchLocation::WindowsDirectory),
+            "cwd" => return Some(SearchLocation::CurrentDirectory),
+            "path" => return Some(SearchLocation::PathDirectories),
+            "user" => return Some(SearchLocation::UserDirectories),
+            _ => return None,
+        }
+    }
+}
+
+/* Default order, SafeDllSearchMode being enabled by default since Windows XP SP2 */
+pub const SAFE_DLL_SEARCH_ORDER: &[SearchLocation] = &[
+    SearchLocation::ApplicationDirectory,
+    SearchLocation::System32Directory,
+    SearchLocation::SystemDirectory,
+    SearchLocation::WindowsDirectory,
+    SearchLocation::CurrentDirectory,
+    SearchLocation::PathDirectories,
+    SearchLocation::UserDirectories,
+];
+
+/* Order when SafeDllSearchMode is disabled, the current directory coming right after the app */
+pub const UNSAFE_DLL_SEARCH_ORDER: &[SearchLocation] = &[
+    SearchLocation::ApplicationDirectory,
+    SearchLocation::CurrentDirectory,
+    SearchLocation::System32Directory,
+    SearchLocation::SystemDirectory,
+    SearchLocation::WindowsDirectory,
+    SearchLocation::PathDirectories,
+    SearchLocation::UserDirectories,
+];
+
+/*
+ * Search order model. The Windows root can point to the Windows directory of an extracted image
+ * to emulate the loader on another system
+ */
+#[derive(Clone, Debug, Default)]
+pub struct SearchOrder {
+    pub locations: Vec<SearchLocation>,
+    pub application_directory: Option<PathBuf>,
+    pub windows_root: Option<PathBuf>,
+    pub current_directory: Option<PathBuf>,
+    pub path_directories: Vec<PathBuf>,
+    pub user_directories: Vec<PathBuf>,
+    /* Side-by-side assemblies store, <windows>/WinSxS by default */
+    pub winsxs_directory: Option<PathBuf>,
+    /* WOW64 file system redirection, for 32-bit executables on 64-bit Windows */
+    pub wow64: bool,
+}
+
+impl SearchOrder {
+    /*
+     * Creates the search order the loader would use for an application, from the current
+     * environment (Windows directory, current directory and PATH)
+     */
+    pub fn new(application_directory: Option<PathBuf>, safe_dll_search_mode: bool) -> SearchOrder {
+        let locations = if safe_dll_search_mode {
+            SAFE_DLL_SEARCH_ORDER
+        } else {
+            UNSAFE_DLL_SEARCH_ORDER
+        };
+
+        let path_directories = match std::env::var_os("PATH") {
+            Some(path) => std::env::split_paths(&path).collect(),
+            None => Vec::new(),
+        };
+
+        return SearchOrder {
+            locations: locations.to_vec(),
+            application_directory,
+            windows_root: get_default_windows_root(),
+            current_directory: std::env::current_dir().ok(),
+            path_directories,
+            user_directories: Vec::new(),
+            winsxs_directory: None,
+            wow64: false,
+        };
+    }
+
+    /*
+     * System directory of the emulated process, SysWOW64 for 32-bit processes
+     */
+    pub fn get_system32_directory(&self) -> Option<PathBuf> {
+        if self.wow64
+            && let Some(wow64_directory) = self.get_wow64_directory()
+        {
+            return Some(wow64_directory);
+        }
+
+        return self.get_native_system32_directory();
+    }
+
+    pub fn get_native_system32_directory(&self) -> Option<PathBuf> {
+        return find_directory_entry(self.windows_root.as_ref()?, "System32");
+    }
+
+    /* Only found on 64-bit Windows */
+    pub fn get_wow64_directory(&self) -> Option<PathBuf> {
+        return find_directory_entry(self.windows_root.as_ref()?, "SysWOW64");
+    }
+
+    /*
+     * Applies the WOW64 file system redirection to a directory: System32 and its subdirectories
+     * map to SysWOW64, except a few shared ones, and Sysnative, that only exists for 32-bit
+     * processes, gives access to the native System32
+     */
+    pub fn redirect_directory(&self, directory: &Path) -> PathBuf {
+        if !self.wow64 {
+            return directory.to_path_buf();
+        }
+
+        let Some(relative_path) = self
+            .windows_root
+            .as_ref()
+            .and_then(|windows_root| strip_prefix_ignore_case(directory, windows_root))
+        else {
+            return directory.to_path_buf();
+        };
+
+        let mut components = relative_path.components();
+
+        let first_component = components
+            .next()
+            .and_then(|component| component.as_os_str().to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        let system_relative_path = components.as_path();
+
+        let redirected_directory = match first_component.as_str() {
+            "system32" if !is_wow64_redirection_exempt(system_relative_path) => {
+                self.get_wow64_directory()
+            }
+            "sysnative" => self.get_native_system32_directory(),
+            _ => None,
+        };
+
+        return match redirected_directory {
+            Some(redirected_directory) if system_relative_path.as_os_str().is_empty() => {
+                redirected_directory
+            }
+            Some(redirected_directory) => redirected_directory.join(system_relative_path),
+            None => directory.to_path_buf(),
+        };
+    }
+
+    pub fn get_system_directory(&self) -> Option<PathBuf> {
+        return find_directory_entry(self.windows_root.as_ref()?, "System");
+    }
+
+    pub fn get_winsxs_directory(&self) -> Option<PathBuf> {
+        if self.winsxs_directory.is_some() {
+            return self.winsxs_directory.clone();
+        }
+
+        return find_directory_entry(self.windows_root.as_ref()?, "WinSxS");
+    }
+
+    /*
+     * Expands the search locations into the ordered list of existing directories to look into
+     */
+    pub fn get_directories(&self) -> Vec<PathBuf> {
+        let mut directories: Vec<PathBuf> = Vec::new();
+        let mut discarded_directories: Vec<PathBuf> = Vec::new();
+
+        for location in &self.locations {
+            let location_directories: Vec<PathBuf> = match location {
+                SearchLocation::ApplicationDirectory => {
+                    self.application_directory.iter().cloned().collect()
+                }
+                SearchLocation::System32Directory => {
+                    self.get_system32_directory().into_iter().collect()
+                }
+                SearchLocation::SystemDirectory => {
+                    self.get_system_directory().into_iter().collect()
+                }
+                SearchLocation::WindowsDirectory => self.windows_root.iter().cloned().collect(),
+                SearchLocation::CurrentDirectory => {
+                    self.current_directory.iter().cloned().collect()
+                }
+                SearchLocation::PathDirectories => self.path_directories.clone(),
+                SearchLocation::UserDirectories => self.user_directories.clone(),
+            };
+
+            for directory in location_directories {
+                let directory = self.redirect_directory(&directory);
+
+                if !directory.is_dir() {
+                    /* The same directory can come from several locations, warn only once */
+                    if !discarded_directories.contains(&directory) {
+                        log::warn!("Path \"{}\" not found, discarding it", directory.display());
+                        discarded_directories.push(directory);
+                    }
+
+                    continue;
+                }
+
+                if !directories.contains(&directory) {
+                    directories.push(directory);
+                }
+            }
+        }
+
+        log::debug!("Search directories: {:?}", directories);
+
+        return directories;
+    }
+}
+
+/*
+ * Subdirectories of System32 shared by 32-bit and 64-bit processes
+ */
+const WOW64_REDIRECTION_EXEMPT_DIRECTORIES: &[&str] = &[
+    "catroot",
+    "catroot2",
+    "driverstore",
+    "drivers/etc",
+    "logfiles",
+    "spool",
+];
+
+fn is_wow64_redirection_exempt(system_relative_path: &Path) -> bool {
+    return WOW64_REDIRECTION_EXEMPT_DIRECTORIES
+        .iter()
+        .any(|exempt_directory| {
+            strip_prefix_ignore_case(system_relative_path, Path::new(exempt_directory)).is_some()
+        });
+}
+
+/*
+ * Path::strip_prefix ignoring the ASCII case of the components, as paths on Windows
+ */
+fn strip_prefix_ignore_case<'a>(path: &'a Path, prefix: &Path) -> Option<&'a Path> {
+    let mut components = path.components();
+
+    for prefix_component in prefix.components() {
+        let component = components.next()?;
+
+        if !component
+            .as_os_str()
+            .eq_ignore_ascii_case(prefix_component.as_os_str())
+        {
+            return None;
+        }
+    }
+
+    return Some(components.as_path());
+}
+
+/*
+ * Windows directory of the running system, if fdw runs on Windows
+ */
+pub fn get_default_windows_root() -> Option<PathBuf> {
+    if !cfg!(windows) {
+        return None;
+    }
+
+    return match std::env::var_os("SystemRoot").or_else(|| std::env::var_os("windir")) {
+        Some(root) => Some(PathBuf::from(root)),
+        None => Some(PathBuf::from("C:\\Windows")),
+    };
+}
+
+/*
+ * Finds an entry of a directory ignoring the case, as Windows does. Extracted Windows images do not
+ * always keep the original case ("system32", "SYSTEM32", ...)
+ */
+pub fn find_directory_entry(directory: &Path, name: &str) -> Option<PathBuf> {
+    let exact_path = directory.join(name);
+
+    if exact_path.exists() {
+        return Some(exact_path);
+    }
+
+    for entry in std::fs::read_dir(directory).ok()?.flatten() {
+        if entry.file_name().to_str()?.eq_ignore_ascii_case(name) {
+            return Some(entry.path());
+        }
+    }
+
+    return None;
+}
+
+/*
+ * Files of the directories looked into, indexed by lowercase name. Each directory is read once, the
+ * first time a dll is looked for in it
+ */
+#[derive(Default, Debug)]
+struct DirectoryIndex {
+    directories: RefCell<HashMap<PathBuf, HashMap<String, PathBuf>>>,
+}
+
+impl DirectoryIndex {
+    fn find(&self, directory: &Path, name: &str) -> Option<PathBuf> {
+        let mut directories = self.directories.borrow_mut();
+
+        let entries = directories
+            .entry(directory.to_path_buf())
+            .or_insert_with(|| read_directory_entries(directory));
+
+        return entries.get(&name.to_ascii_lowercase()).cloned();
+    }
+}
+
+fn read_directory_entries(directory: &Path) -> HashMap<String, PathBuf> {
+    log::trace!("Indexing directory: {}", directory.display());
+
+    let mut entries: HashMap<String, PathBuf> = HashMap::new();
+
+    let directory_entries = match std::fs::read_dir(directory) {
+        Ok(e) => e,
+        Err(err) => {
+            log::trace!(
+                "Cannot read entries of directory: {} ({})",
+                directory.display(),
+                err
+            );
+            return entries;
+        }
+    };
+
+    for entry in directory_entries {
+        let file = match entry {
+            Ok(f) => f,
             Err(err) => {
                 log::trace!(
-                    "Cannot read entries of directory: {} ({})",
-                    path.display(),
+                    "Cannot read entry in directory: {} ({})",
+                    directory.display(),
                     err
                 );
                 continue;
             }
         };
 
-        for entry in entries {
-            let file = match entry {
-                Ok(f) => f,
-                Err(err) => {
-                    log::trace!(
-                        "Cannot read entry in directory: {} ({})",
-                        path.display(),
-                        err
-                    );
-                    continue;
-                }
+        let file_path = file.path();
+
+        if !file_path.is_file() {
+            continue;
+        }
+
+        if let Some(file_name) = file.file_name().to_str() {
+            entries
+                .entry(file_name.to_ascii_lowercase())
+                .or_insert(file_path);
+        }
+    }
+
+    return entries;
+}
+
+/*
+ * Resolves dll names to files, going through the search directories in order. File names are
+ * matched exactly, ignoring the ASCII case like Windows does
+ */
+#[derive(Default, Debug)]
+pub struct DllResolver {
+    search_paths: Vec<PathBuf>,
+    directory_index: DirectoryIndex,
+    assembly_resolver: super::sxs::AssemblyResolver,
+    /* Activation context of the executable, used by modules without their own manifest */
+    process_activation_context: super::sxs::ActivationContext,
+    executable_path: Option<PathBuf>,
+    /* <app>.exe.local file or directory, when dll redirection applies */
+    dot_local_path: Option<PathBuf>,
+    known_dlls: super::knowndlls::KnownDlls,
+    system32_directory: Option<PathBuf>,
+    /* Machine of the executable, dlls built for another one are skipped */
+    machine: super::pe::Machine,
+    /* Files parsed so far, as they are looked at again for each module importing them */
+    pes: RefCell<HashMap<PathBuf, Result<Rc<super::pe::PE>, String>>>,
+    /* Files the dependency walk went through, in the order they were first reached */
+    walked_paths: RefCell<Vec<PathBuf>>,
+}
+
+impl DllResolver {
+    pub fn new(search_order: &SearchOrder) -> DllResolver {
+        return DllResolver {
+            search_paths: search_order.get_directories(),
+            directory_index: DirectoryIndex::default(),
+            assembly_resolver: super::sxs::AssemblyResolver::new(
+                search_order.get_winsxs_directory(),
+            ),
+            process_activation_context: super::sxs::ActivationContext::new(),
+            executable_path: None,
+            dot_local_path: None,
+            known_dlls: super::knowndlls::KnownDlls::new(),
+            system32_directory: search_order.get_system32_directory(),
+            machine: super::pe::Machine::Unknown,
+            pes: RefCell::new(HashMap::new()),
+            walked_paths: RefCell::new(Vec::new()),
+        };
+    }
+
+    /*
+     * Sets up what applies to the whole process from the executable: its machine and its activation
+     * context, from the embedded manifest or else an external <app>.exe.manifest, with the probing
+     * settings of <app>.exe.config. Without any manifest, <app>.exe.local enables dll redirection
+     */
+    pub fn load_executable(&mut self, pe: &super::pe::PE, pe_path: &Path) {
+        self.machine = pe.get_machine();
+
+        let mut manifest = super::manifest::get_pe_manifest(pe);
+
+        if manifest.is_none() {
+            let external_manifest_path = get_sibling_path(pe_path, "manifest");
+
+            manifest = super::manifest::Manifest::from_file(&external_manifest_path);
+
+            if manifest.is_some() {
+                log::info!(
+                    "Using external manifest: {}",
+                    external_manifest_path.display()
+                );
+            }
+        }
+
+        self.executable_path = Some(pe_path.to_path_buf());
+
+        let Some(manifest) = manifest else {
+            let dot_local_path = get_sibling_path(pe_path, "local");
+
+            if dot_local_path.exists() {
+                log::info!("Using dll redirection: {}", dot_local_path.display());
+
+                self.dot_local_path = Some(dot_local_path);
+            }
+
+            return;
+        };
+
+        let mut probing = super::sxs::AssemblyProbing::new(
+            pe_path.parent(),
+            super::sxs::get_processor_architecture(pe),
+        );
+
+        probing.load_configuration(&get_sibling_path(pe_path, "config"));
+
+        self.process_activation_context = super::sxs::ActivationContext::from_manifest(
+            &manifest,
+            &probing,
+            &self.assembly_resolver,
+        );
+    }
+
+    /*
+     * Activation context from the manifest embedded in a PE, None if it does not depend on any
+     * assembly. Private assemblies are looked for next to the PE
+     */
+    pub fn get_activation_context(
+        &self,
+        pe: &super::pe::PE,
+        pe_path: &Path,
+    ) -> Option<super::sxs::ActivationContext> {
+        if self.executable_path.as_deref() == Some(pe_path) {
+            return match self.process_activation_context.is_empty() {
+                true => None,
+                false => Some(self.process_activation_context.clone()),
             };
+        }
+
+        let manifest = super::manifest::get_pe_manifest(pe)?;
+
+        if manifest.dependencies.is_empty() {
+            return None;
+        }
+
+        let probing = super::sxs::AssemblyProbing::new(
+            pe_path.parent(),
+            super::sxs::get_processor_architecture(pe),
+        );
+
+        return Some(super::sxs::ActivationContext::from_manifest(
+            &manifest,
+            &probing,
+            &self.assembly_resolver,
+        ));
+    }
+
+    /*
+     * With dll redirection, dlls are loaded from the .local directory, or from the application
+     * directory when .local is a file, before anything else
+     */
+    fn find_redirected_dll(&self, name: &str) -> Option<PathBuf> {
+        let dot_local_path = self.dot_local_path.as_ref()?;
+
+        let directory = match dot_local_path.is_dir() {
+            true => dot_local_path.as_path(),
+            false => dot_local_path.parent()?,
+        };
+
+        return self.directory_index.find(directory, name);
+    }
+
+    /*
+     * Known DLLs are always loaded from System32, whatever the search order
+     */
+    fn find_known_dll(&self, name: &str) -> Option<PathBuf> {
+        if !self.known_dlls.contains(name) {
+            return None;
+        }
+
+        return self
+            .directory_index
+            .find(self.system32_directory.as_ref()?, name);
+    }
+
+    /*
+     * Parses a file once, the PE being shared by every lookup of that file. Only the headers are
+     * parsed upfront, the directories being parsed when they are first needed
+     */
+    pub fn get_pe(&self, file_path: &Path) -> Result<Rc<super::pe::PE>, String> {
+        return self
+            .pes
+            .borrow_mut()
+            .entry(file_path.to_path_buf())
+            .or_insert_with(|| {
+                super::pe::parse_pe(file_path)
+                    .map(Rc::new)
+                    .map_err(|err| err.to_string())
+            })
+            .clone();
+    }
 
-            if !file.file_type().unwrap().is_file() {
+    /* Files are recorded once, the dependency walk can reach a dll from several importers */
+    fn record_walked_path(&self, file_path: &Path) {
+        let mut walked_paths = self.walked_paths.borrow_mut();
+
+        if !walked_paths
+            .iter()
+            .any(|walked_path| walked_path == file_path)
+        {
+            walked_paths.push(file_path.to_path_buf());
+        }
+    }
+
+    /*
+     * Machine of a dll. Files that cannot be parsed are not skipped, the loader would fail on them
+     */
+    fn get_dll_machine(&self, file_path: &Path) -> Option<super::pe::Machine> {
+        return self.get_pe(file_path).ok().map(|pe| pe.get_machine());
+    }
+
+    /*
+     * Goes through the search directories, skipping the dlls built for another machine than the
+     * executable like the loader does
+     */
+    fn find_compatible_dll(&self, name: &str) -> (Option<PathBuf>, Vec<SkippedDll>) {
+        let mut skipped_dlls: Vec<SkippedDll> = Vec::new();
+
+        for path in self.search_paths.iter() {
+            let Some(file_path) = self.directory_index.find(path, name) else {
                 continue;
+            };
+
+            match self.get_dll_machine(&file_path) {
+                Some(machine) if !is_machine_compatible(self.machine, machine) => {
+                    log::debug!("Skipping {} ({machine} image)", file_path.display());
+
+                    skipped_dlls.push(SkippedDll {
+                        path: file_path,
+                        machine,
+                    });
+                }
+                _ => return (Some(file_path), skipped_dlls),
             }
+        }
+
+        return (None, skipped_dlls);
+    }
+
+    pub fn find_dll(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        log::trace!("find_dll(): Looking for dll: {name}");
+
+        return match self.find_compatible_dll(name).0 {
+            Some(file_path) => Ok(file_path.to_str().unwrap_or("<invalid utf-8>").to_string()),
+            None => Err("Cannot find dll file in provided search paths".into()),
+        };
+    }
+}
+
+/* Guards against forwarder cycles between dlls */
+const MAX_FORWARDER_DEPTH: usize = 16;
+
+fn imported_symbol_to_json(symbol: &super::pe::ImportedSymbol) -> json::JsonValue {
+    match symbol {
+        super::pe::ImportedSymbol::ByName { hint, name } => {
+            return json::object! {
+                name: name.as_str(),
+                hint: *hint
+            };
+        }
+        super::pe::ImportedSymbol::ByOrdinal(ordinal) => {
+            return json::object! {
+                ordinal: *ordinal
+            };
+        }
+    }
+}
+
+fn imported_symbols_to_json(symbols: &[super::pe::ImportedSymbol]) -> json::JsonValue {
+    return json::JsonValue::Array(symbols.iter().map(imported_symbol_to_json).collect());
+}
+
+/*
+ * Version resource of a PE, null when it has none
+ */
+fn version_info_to_json(pe: &super::pe::PE) -> json::JsonValue {
+    let version_info = match pe.get_version_info() {
+        Some(version_info) => version_info,
+        None => return json::JsonValue::Null,
+    };
 
-            if file
-                .file_name()
-                .to_str()
-                .unwrap_or("<invalid utf-8>")
-                .ends_with(name)
-            {
-                return Ok(file
-                    .path()
+    let mut strings = json::JsonValue::new_object();
+
+    for (key, value) in &version_info.strings {
+        strings[key.as_str()] = value.as_str().into();
+    }
+
+    let mut version = json::object! {
+        strings: strings,
+    };
+
+    if let Some(fixed_file_info) = &version_info.fixed_file_info {
+        version["file_version"] = fixed_file_info.get_file_version().into();
+        version["product_version"] = fixed_file_info.get_product_version().into();
+        version["file_flags"] = fixed_file_info.get_file_flags().into();
+        version["file_os"] = fixed_file_info.get_file_os().into();
+        version["file_type"] = fixed_file_info.get_file_type().into();
+        version["file_subtype"] = fixed_file_info.get_file_subtype().into();
+    }
+
+    return version;
+}
+
+/*
+ * Version of a dll that is not walked, null when it cannot be parsed
+ */
+fn get_dll_version(dll_path: &Path, resolver: &DllResolver) -> json::JsonValue {
+    return match resolver.get_pe(dll_path) {
+        Ok(pe) => version_info_to_json(&pe),
+        Err(_) => json::JsonValue::Null,
+    };
+}
+
+/*
+ * Dlls of a process have to be built for its machine. ARM64X dlls hold both ARM64 and ARM64EC
+ * code, and ARM64EC code can be mixed with x64 code. ARM64X executables run as ARM64 processes
+ */
+fn is_machine_compatible(process_machine: super::pe::Machine, machine: super::pe::Machine) -> bool {
+    use super::pe::Machine;
+
+    if process_machine == Machine::Unknown || machine == process_machine {
+        return true;
+    }
+
+    return match process_machine {
+        Machine::Amd64 | Machine::Arm64Ec => {
+            matches!(machine, Machine::Amd64 | Machine::Arm64Ec | Machine::Arm64X)
+        }
+        Machine::Arm64 | Machine::Arm64X => matches!(machine, Machine::Arm64 | Machine::Arm64X),
+        _ => false,
+    };
+}
+
+/*
+ * Dll of the search order skipped because it was built for another machine
+ */
+#[derive(Debug)]
+struct SkippedDll {
+    path: PathBuf,
+    machine: super::pe::Machine,
+}
+
+fn skipped_dlls_to_json(
+    skipped_dlls: &[SkippedDll],
+    process_machine: super::pe::Machine,
+) -> json::JsonValue {
+    return json::JsonValue::Array(
+        skipped_dlls
+            .iter()
+            .map(|skipped_dll| {
+                format!(
+                    "Skipped {} ({} image, the process is {})",
+                    skipped_dll.path.display(),
+                    skipped_dll.machine,
+                    process_machine
+                )
+                .into()
+            })
+            .collect(),
+    );
+}
+
+/*
+ * <app>.exe.manifest, <app>.exe.config, <app>.exe.local...
+ */
+fn get_sibling_path(pe_path: &Path, extension: &str) -> PathBuf {
+    let mut sibling_path = pe_path.as_os_str().to_os_string();
+    sibling_path.push(".");
+    sibling_path.push(extension);
+
+    return PathBuf::from(sibling_path);
+}
+
+/*
+ * Dll found for an import, with the side-by-side assembly it comes from if any, and the .local or
+ * configuration file that redirected it. For Known DLLs, the copy the search order would have
+ * found instead of the one of System32 is kept to be reported
+ */
+#[derive(Default, Debug)]
+struct ResolvedDll {
+    path: String,
+    assembly: Option<String>,
+    redirection: Option<String>,
+    known_dll: bool,
+    shadowed_path: Option<String>,
+    skipped_dlls: Vec<SkippedDll>,
+}
+
+/*
+ * Dll that cannot be found, with the files of the search order that were skipped
+ */
+#[derive(Default, Debug)]
+struct UnresolvedDll {
+    skipped_dlls: Vec<SkippedDll>,
+}
+
+/*
+ * Resolves a dll name to a path, going through the API Set schema first if needed, then the .local
+ * redirection, the side-by-side assemblies of the activation context (the one of the executable
+ * when None) and the Known DLLs. The importer is the name of the module importing the dll, which
+ * can have its own host in the API Set schema
+ */
+fn resolve_dll(
+    name: &str,
+    importer: &str,
+    activation_context: Option<&super::sxs::ActivationContext>,
+    resolver: &DllResolver,
+    apiset_schema: &super::apiset::APISet,
+) -> Result<ResolvedDll, UnresolvedDll> {
+    let mut name = name.to_string();
+
+    if super::apiset::is_dll_from_apiset_schema(&name)
+        && let Some(host_name) = super::apiset::find_dll(&name, apiset_schema, Some(importer))
+    {
+        name = host_name;
+    }
+
+    let known_dll = resolver.known_dlls.contains(&name);
+
+    /* Known DLLs cannot be redirected */
+    if !known_dll && let Some(file_path) = resolver.find_redirected_dll(&name) {
+        return Ok(ResolvedDll {
+            path: file_path.to_str().unwrap_or("<invalid utf-8>").to_string(),
+            assembly: None,
+            redirection: resolver.dot_local_path.as_ref().map(|dot_local_path| {
+                dot_local_path
                     .to_str()
                     .unwrap_or("<invalid utf-8>")
-                    .to_string());
-            }
+                    .to_string()
+            }),
+            known_dll,
+            shadowed_path: None,
+            skipped_dlls: Vec::new(),
+        });
+    }
+
+    let activation_context = activation_context.unwrap_or(&resolver.process_activation_context);
+
+    if let Some((file_path, assembly)) = activation_context.find_dll(&name) {
+        return Ok(ResolvedDll {
+            path: file_path.to_str().unwrap_or("<invalid utf-8>").to_string(),
+            assembly: Some(assembly.identity.to_string()),
+            redirection: assembly.redirection.as_ref().map(|redirection| {
+                redirection
+                    .to_str()
+                    .unwrap_or("<invalid utf-8>")
+                    .to_string()
+            }),
+            known_dll,
+            shadowed_path: None,
+            skipped_dlls: Vec::new(),
+        });
+    }
+
+    if let Some(file_path) = resolver.find_known_dll(&name) {
+        let file_path = file_path.to_str().unwrap_or("<invalid utf-8>").to_string();
+
+        return Ok(ResolvedDll {
+            shadowed_path: resolver
+                .find_dll(&name)
+                .ok()
+                .filter(|shadowed_path| *shadowed_path != file_path),
+            path: file_path,
+            assembly: None,
+            redirection: None,
+            known_dll,
+            skipped_dlls: Vec::new(),
+        });
+    }
+
+    if known_dll {
+        log::debug!("Known DLL {name} not found in System32, using the search order");
+    }
+
+    let (file_path, skipped_dlls) = resolver.find_compatible_dll(&name);
+
+    let Some(file_path) = file_path else {
+        return Err(UnresolvedDll { skipped_dlls });
+    };
+
+    return Ok(ResolvedDll {
+        path: file_path.to_str().unwrap_or("<invalid utf-8>").to_string(),
+        assembly: None,
+        redirection: None,
+        known_dll,
+        shadowed_path: None,
+        skipped_dlls,
+    });
+}
+
+/*
+ * Checks that a symbol is exported by a dll, following forwarders to their target dll like the
+ * loader does
+ */
+fn verify_symbol(
+    symbol: &super::pe::ImportedSymbol,
+    dll_path: &Path,
+    resolver: &DllResolver,
+    apiset_schema: &super::apiset::APISet,
+    depth: usize,
+) -> Result<(), String> {
+    if depth > MAX_FORWARDER_DEPTH {
+        return Err("forwarder chain is too deep".to_string());
+    }
+
+    let pe = resolver
+        .get_pe(dll_path)
+        .map_err(|err| format!("cannot parse {} ({})", dll_path.display(), err))?;
+
+    let forwarder = match pe.get_exports().and_then(|exports| exports.find(symbol)) {
+        Some(export) => match &export.forwarder {
+            Some(forwarder) => forwarder.clone(),
+            None => return Ok(()),
+        },
+        None => {
+            return Err(format!("not exported by {}", dll_path.display()));
         }
+    };
+
+    let (module, target_symbol) = super::pe::ImportedSymbol::from_forwarder(&forwarder)
+        .ok_or(format!("invalid forwarder \"{forwarder}\""))?;
+
+    let mut module_name = module.to_ascii_lowercase();
+
+    if !module_name.ends_with(".dll") {
+        module_name.push_str(".dll");
+    }
+
+    let importer = dll_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    let target = resolve_dll(&module_name, &importer, None, resolver, apiset_schema)
+        .map_err(|_| format!("forwarded to {forwarder}, but {module_name} cannot be found"))?;
+
+    return verify_symbol(
+        &target_symbol,
+        &PathBuf::from(target.path),
+        resolver,
+        apiset_schema,
+        depth + 1,
+    )
+    .map_err(|err| format!("forwarded to {forwarder}, {err}"));
+}
+
+/*
+ * Returns the imported symbols that cannot be found in the resolved dll, with the reason why
+ */
+fn find_missing_symbols(
+    symbols: &[super::pe::ImportedSymbol],
+    dll_path: &Path,
+    resolver: &DllResolver,
+    apiset_schema: &super::apiset::APISet,
+) -> json::JsonValue {
+    let mut missing_symbols: Vec<json::JsonValue> = Vec::new();
+
+    for symbol in symbols {
+        if let Err(reason) = verify_symbol(symbol, dll_path, resolver, apiset_schema, 0) {
+            log::debug!(
+                "Unresolved symbol {symbol} in {}: {reason}",
+                dll_path.display()
+            );
+
+            let mut missing_symbol = imported_symbol_to_json(symbol);
+            missing_symbol["reason"] = reason.into();
+
+            missing_symbols.push(missing_symbol);
+        }
+    }
+
+    return json::JsonValue::Array(missing_symbols);
+}
+
+/*
+ * Dependency that cannot be found. Extension API Sets without a host in a complete schema are
+ * reported as absent, as they are optional
+ */
+fn unresolved_node(
+    name: &str,
+    imported_dll: &super::pe::ImportedDll,
+    unresolved_dll: &UnresolvedDll,
+    resolver: &DllResolver,
+    apiset_schema: &super::apiset::APISet,
+) -> json::JsonValue {
+    let mut dep_object = json::object! {
+        name: name,
+        path: "<unknown>",
+        delay_load: imported_dll.delay_load,
+        imports: imported_symbols_to_json(&imported_dll.symbols),
+    };
+
+    if super::apiset::is_absent_extension(name, apiset_schema) {
+        dep_object["path"] = "<absent extension>".into();
+        dep_object["absent_extension"] = true.into();
     }
 
-    return Err("Cannot find dll file in provided search paths".into());
+    if !unresolved_dll.skipped_dlls.is_empty() {
+        dep_object["warnings"] =
+            skipped_dlls_to_json(&unresolved_dll.skipped_dlls, resolver.machine);
+    }
+
+    return dep_object;
 }
 
 fn get_dll_dependencies(
-    pe_path: &PathBuf,
-    search_paths: &[PathBuf],
+    pe_path: &Path,
+    resolver: &DllResolver,
     apiset_schema: &super::apiset::APISet,
+    verify_symbols: bool,
 ) -> Result<json::JsonValue, Box<dyn std::error::Error>> {
-    let pe = super::pe::parse_pe(&pe_path)
+    resolver.record_walked_path(pe_path);
+
+    let pe = resolver
+        .get_pe(pe_path)
+        .map_err(|err| format!("Failed to parse PE \"{}\" ({})", pe_path.display(), err))?;
+
+    let imported_dlls = pe
+        .get_imported_dlls()
         .map_err(|err| format!("Failed to parse PE \"{}\" ({})", pe_path.display(), err))?;
 
     let pe_name = pe_path
@@ -70,41 +987,128 @@ fn get_dll_dependencies(
 
     log::trace!("get_dll_dependencies(): Looking for dll dependencies: {pe_name}");
 
+    let activation_context = resolver.get_activation_context(&pe, pe_path);
+
     let mut dependencies_array: Vec<json::JsonValue> = Vec::new();
 
-    for dll_name in &pe.dll_names {
-        let lower = dll_name.to_ascii_lowercase();
+    for imported_dll in imported_dlls {
+        let lower = imported_dll.name.to_ascii_lowercase();
 
-        let resolved_path = match super::apiset::is_dll_from_apiset_schema(&lower) {
-            true => find_dll(
-                &super::apiset::find_dll(&lower, apiset_schema).unwrap_or("<unknown>".to_string()),
-                search_paths,
-            )
-            .unwrap_or("<unknown>".to_string()),
-            false => find_dll(&lower, search_paths).unwrap_or("<unknown>".to_string()),
+        let dep_object = match resolve_dll(
+            &lower,
+            &pe_name,
+            activation_context.as_ref(),
+            resolver,
+            apiset_schema,
+        ) {
+            Ok(resolved_dll) => {
+                resolver.record_walked_path(Path::new(&resolved_dll.path));
+
+                let mut dep_object = json::object! {
+                    name: lower.clone(),
+                    path: resolved_dll.path.clone(),
+                    version: get_dll_version(Path::new(&resolved_dll.path), resolver),
+                    delay_load: imported_dll.delay_load,
+                    imports: imported_symbols_to_json(&imported_dll.symbols)
+                };
+
+                if let Some(assembly) = resolved_dll.assembly {
+                    dep_object["assembly"] = assembly.into();
+                }
+
+                if let Some(redirection) = resolved_dll.redirection {
+                    dep_object["redirection"] = redirection.into();
+                }
+
+                if resolved_dll.known_dll {
+                    dep_object["known_dll"] = true.into();
+                }
+
+                if let Some(shadowed_path) = resolved_dll.shadowed_path {
+                    log::warn!("{lower} is a Known DLL, {shadowed_path} is shadowed");
+                    dep_object["shadowed_path"] = shadowed_path.into();
+                }
+
+                if !resolved_dll.skipped_dlls.is_empty() {
+                    dep_object["warnings"] =
+                        skipped_dlls_to_json(&resolved_dll.skipped_dlls, resolver.machine);
+                }
+
+                if verify_symbols {
+                    dep_object["missing_symbols"] = find_missing_symbols(
+                        &imported_dll.symbols,
+                        &PathBuf::from(resolved_dll.path),
+                        resolver,
+                        apiset_schema,
+                    );
+                }
+
+                dep_object
+            }
+            Err(unresolved_dll) => unresolved_node(
+                &lower,
+                imported_dll,
+                &unresolved_dll,
+                resolver,
+                apiset_schema,
+            ),
         };
 
-        dependencies_array.push(json::object! {
-            name: lower,
-            path: resolved_path
-        });
+        dependencies_array.push(dep_object);
     }
 
-    let result = json::object! {
+    let mut result = json::object! {
         name: pe_name,
         path: pe_path.to_str().unwrap_or("<invalid utf-8>"),
+        version: version_info_to_json(&pe),
         dependencies: json::JsonValue::Array(dependencies_array)
     };
 
+    if let Some(activation_context) = &activation_context {
+        result["assemblies"] = activation_context.to_json();
+    }
+
     return Ok(result);
 }
 
+/*
+ * Dependency node that could not be walked, recording why
+ */
+fn error_node(name: &str, pe_path: &Path, error: &str) -> json::JsonValue {
+    return json::object! {
+        name: name,
+        path: pe_path.to_str().unwrap_or("<invalid path>"),
+        error: error,
+    };
+}
+
+/*
+ * Records a file that cannot be walked, so that it is not parsed again from other importers
+ */
+fn record_parse_failure(
+    pe_path: &PathBuf,
+    pe_name: &str,
+    err: &dyn std::fmt::Display,
+    cache: &mut HashMap<PathBuf, json::JsonValue>,
+    visited: &mut HashSet<PathBuf>,
+) -> Box<dyn std::error::Error> {
+    let error = format!("Failed to parse PE \"{}\" ({})", pe_path.display(), err);
+
+    log::warn!("{error}");
+
+    visited.remove(pe_path);
+    cache.insert(pe_path.clone(), error_node(pe_name, pe_path, &error));
+
+    return error.into();
+}
+
 fn get_dll_dependencies_recursive(
     pe_path: &PathBuf,
-    search_paths: &[PathBuf],
+    resolver: &DllResolver,
     apiset_schema: &super::apiset::APISet,
     cache: &mut HashMap<PathBuf, json::JsonValue>,
     visited: &mut HashSet<PathBuf>,
+    verify_symbols: bool,
 ) -> Result<json::JsonValue, Box<dyn std::error::Error>> {
     if let Some(cached) = cache.get(pe_path) {
         return Ok(cached.clone());
@@ -114,8 +1118,7 @@ fn get_dll_dependencies_recursive(
         return Err(format!("Circular dependency detected in dll: {}", pe_path.display()).into());
     }
 
-    let pe = super::pe::parse_pe(&pe_path)
-        .map_err(|err| format!("Failed to parse PE \"{}\" ({})", pe_path.display(), err))?;
+    resolver.record_walked_path(pe_path);
 
     let pe_name = pe_path
         .file_name()
@@ -123,81 +1126,710 @@ fn get_dll_dependencies_recursive(
         .unwrap_or("<unknown>")
         .to_ascii_lowercase();
 
+    let pe = match resolver.get_pe(pe_path) {
+        Ok(pe) => pe,
+        Err(err) => {
+            return Err(record_parse_failure(
+                pe_path, &pe_name, &err, cache, visited,
+            ));
+        }
+    };
+
+    let imported_dlls = match pe.get_imported_dlls() {
+        Ok(imported_dlls) => imported_dlls,
+        Err(err) => return Err(record_parse_failure(pe_path, &pe_name, err, cache, visited)),
+    };
+
     log::trace!("get_dll_dependencies_recursive(): Looking for dll dependencies: {pe_name}");
 
-    let mut dependencies: Vec<json::JsonValue> = Vec::new();
+    let activation_context = resolver.get_activation_context(&pe, pe_path);
 
-    for dll_name in &pe.dll_names {
-        let lower = dll_name.to_ascii_lowercase();
+    let mut dependencies: Vec<json::JsonValue> = Vec::new();
 
-        let actual_dll_name = if super::apiset::is_dll_from_apiset_schema(&lower) {
-            match super::apiset::find_dll(&lower, apiset_schema) {
-                Some(name) => name,
-                None => lower.clone(),
-            }
-        } else {
-            lower.clone()
-        };
+    for imported_dll in imported_dlls {
+        let lower = imported_dll.name.to_ascii_lowercase();
 
-        match find_dll(&actual_dll_name, search_paths) {
-            Ok(resolved_path) => {
-                let resolved_pathbuf = PathBuf::from(&resolved_path);
+        match resolve_dll(
+            &lower,
+            &pe_name,
+            activation_context.as_ref(),
+            resolver,
+            apiset_schema,
+        ) {
+            Ok(resolved_dll) => {
+                let resolved_pathbuf = PathBuf::from(&resolved_dll.path);
 
-                let dep_object = match get_dll_dependencies_recursive(
+                let mut dep_object = match get_dll_dependencies_recursive(
                     &resolved_pathbuf,
-                    search_paths,
+                    resolver,
                     apiset_schema,
                     cache,
                     visited,
+                    verify_symbols,
                 ) {
                     Ok(deps) => deps,
-                    Err(e) => json::object! {
-                        name: lower.clone(),
-                        path: resolved_path,
-                        dependencies: format!("Failed to resolve dependencies: {e}")
-                    },
+                    Err(e) => error_node(&lower, &resolved_pathbuf, &e.to_string()),
                 };
 
+                /* Edge attributes belong to the importer, not to the cached dependency node */
+                dep_object["delay_load"] = imported_dll.delay_load.into();
+                dep_object["imports"] = imported_symbols_to_json(&imported_dll.symbols);
+
+                if let Some(assembly) = resolved_dll.assembly {
+                    dep_object["assembly"] = assembly.into();
+                }
+
+                if let Some(redirection) = resolved_dll.redirection {
+                    dep_object["redirection"] = redirection.into();
+                }
+
+                if resolved_dll.known_dll {
+                    dep_object["known_dll"] = true.into();
+                }
+
+                if let Some(shadowed_path) = resolved_dll.shadowed_path {
+                    log::warn!("{lower} is a Known DLL, {shadowed_path} is shadowed");
+                    dep_object["shadowed_path"] = shadowed_path.into();
+                }
+
+                if !resolved_dll.skipped_dlls.is_empty() {
+                    dep_object["warnings"] =
+                        skipped_dlls_to_json(&resolved_dll.skipped_dlls, resolver.machine);
+                }
+
+                if verify_symbols {
+                    dep_object["missing_symbols"] = find_missing_symbols(
+                        &imported_dll.symbols,
+                        &resolved_pathbuf,
+                        resolver,
+                        apiset_schema,
+                    );
+                }
+
                 dependencies.push(dep_object);
             }
-            Err(_) => dependencies.push(json::object! {
-                name: lower.clone(),
-                path: "<unknown>",
-            }),
+            Err(unresolved_dll) => dependencies.push(unresolved_node(
+                &lower,
+                imported_dll,
+                &unresolved_dll,
+                resolver,
+                apiset_schema,
+            )),
         }
     }
 
     visited.remove(pe_path);
 
-    let result = json::object! {
+    let mut result = json::object! {
         name: pe_name,
         path: pe_path.to_str().unwrap_or("<invalid path>"),
+        version: version_info_to_json(&pe),
         dependencies: json::JsonValue::Array(dependencies),
     };
 
+    if let Some(activation_context) = &activation_context {
+        result["assemblies"] = activation_context.to_json();
+    }
+
     cache.insert(pe_path.clone(), result.clone());
 
     return Ok(result);
 }
 
+/*
+ * Result of a dependency walk: the dependency tree, and the files it went through (the root
+ * first, each once) with their parsed PE
+ */
+pub struct DependencyWalk {
+    pub dependencies: json::JsonValue,
+    pub files: Vec<(PathBuf, Result<Rc<super::pe::PE>, String>)>,
+}
+
 pub fn resolve_dependencies(
     pe_path: PathBuf,
-    search_paths: Vec<PathBuf>,
+    search_order: &SearchOrder,
     apiset_schema: super::apiset::APISet,
+    known_dlls: super::knowndlls::KnownDlls,
     recurse: bool,
-) -> Result<json::JsonValue, Box<dyn std::error::Error>> {
-    if recurse {
+    verify_symbols: bool,
+) -> Result<DependencyWalk, Box<dyn std::error::Error>> {
+    let pe = super::pe::parse_pe(&pe_path)
+        .map(Rc::new)
+        .map_err(|err| err.to_string());
+
+    /* 32-bit executables see SysWOW64 as their system directory */
+    let mut search_order = search_order.clone();
+
+    if let Ok(pe) = &pe
+        && pe.is_32_bits()
+        && search_order.get_wow64_directory().is_some()
+    {
+        log::info!("32-bit executable, applying the WOW64 file system redirection");
+
+        search_order.wow64 = true;
+    }
+
+    let mut resolver = DllResolver::new(&search_order);
+    resolver.known_dlls = known_dlls;
+
+    /* Dlls without their own manifest use the activation context of the executable */
+    if let Ok(pe) = &pe {
+        resolver.load_executable(pe, &pe_path);
+    }
+
+    resolver.pes.borrow_mut().insert(pe_path.clone(), pe);
+
+    let dependencies = if recurse {
         let mut cache: HashMap<PathBuf, json::JsonValue> = HashMap::new();
         let mut visited: HashSet<PathBuf> = HashSet::new();
 
-        return get_dll_dependencies_recursive(
+        get_dll_dependencies_recursive(
             &pe_path,
-            &search_paths,
+            &resolver,
             &apiset_schema,
             &mut cache,
             &mut visited,
-        );
+            verify_symbols,
+        )?
     } else {
-        return get_dll_dependencies(&pe_path, &search_paths, &apiset_schema);
+        get_dll_dependencies(&pe_path, &resolver, &apiset_schema, verify_symbols)?
+    };
+
+    let files = resolver
+        .walked_paths
+        .take()
+        .into_iter()
+        .map(|file_path| {
+            let pe = resolver.get_pe(&file_path);
+            (file_path, pe)
+        })
+        .collect();
+
+    return Ok(DependencyWalk {
+        dependencies,
+        files,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pe::test_image::ImageBuilder;
+    use super::super::pe::{IMAGE_FILE_MACHINE_AMD64, ImportedSymbol};
+    use super::super::test_directory::TestDirectory;
+    use super::*;
+
+    /* Exported symbols of a test dll, with their forwarder */
+    type Exports<'a> = &'a [(&'a str, Option<&'a str>)];
+
+    /*
+     * Writes the dlls, given as (file name, exports), to a temporary directory and creates a
+     * resolver that only searches this directory
+     */
+    fn create_resolver(test_name: &str, dlls: &[(&str, Exports)]) -> (TestDirectory, DllResolver) {
+        let directory = TestDirectory::new(test_name);
+
+        for (file_name, exports) in dlls {
+            let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+            image.add_exports(file_name, exports);
+
+            directory.write(file_name, image.build());
+        }
+
+        let mut search_order = SearchOrder::default();
+        search_order.locations = vec![SearchLocation::UserDirectories];
+        search_order.user_directories = vec![directory.get_path().to_path_buf()];
+
+        let resolver = DllResolver::new(&search_order);
+
+        return (directory, resolver);
+    }
+
+    fn by_name(name: &str) -> ImportedSymbol {
+        return ImportedSymbol::ByName {
+            hint: 0,
+            name: name.to_string(),
+        };
+    }
+
+    /* Writes an amd64 executable importing a symbol from each dll */
+    fn write_executable(directory: &TestDirectory, relative_path: &str, dlls: &[&str]) -> PathBuf {
+        let symbols = [by_name("Foo")];
+        let imports: Vec<(&str, &[ImportedSymbol])> =
+            dlls.iter().map(|dll| (*dll, &symbols[..])).collect();
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.set_characteristics(0x0022);
+        image.add_imports(&imports);
+
+        return directory.write(relative_path, image.build());
+    }
+
+    fn write_dll(directory: &TestDirectory, relative_path: &str) -> PathBuf {
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.add_exports(relative_path, &[("Foo", None)]);
+
+        return directory.write(relative_path, image.build());
+    }
+
+    /*
+     * Loads the executable in the resolver and returns the path and redirection output of its
+     * direct dependencies
+     */
+    fn resolve_executable(
+        resolver: &mut DllResolver,
+        app_path: &Path,
+    ) -> Vec<(String, Option<String>)> {
+        let pe = resolver.get_pe(app_path).unwrap();
+        resolver.load_executable(&pe, app_path);
+
+        let apiset_schema = super::super::apiset::APISet::new();
+        let dependencies = get_dll_dependencies(app_path, resolver, &apiset_schema, false).unwrap();
+
+        return dependencies["dependencies"]
+            .members()
+            .map(|dll| {
+                (
+                    dll["path"].to_string(),
+                    dll["redirection"].as_str().map(str::to_string),
+                )
+            })
+            .collect();
+    }
+
+    fn path_string(path: &Path) -> String {
+        return path.to_str().unwrap().to_string();
+    }
+
+    #[test]
+    fn missing_symbols_are_found_by_name_and_ordinal() {
+        let (directory, resolver) =
+            create_resolver("missing", &[("a.dll", &[("Foo", None), ("Bar", None)])]);
+        let apiset_schema = super::super::apiset::APISet::new();
+
+        let symbols = vec![
+            by_name("Foo"),
+            by_name("Baz"),
+            ImportedSymbol::ByOrdinal(2),
+            ImportedSymbol::ByOrdinal(3),
+        ];
+
+        let missing_symbols = find_missing_symbols(
+            &symbols,
+            &directory.get_path().join("a.dll"),
+            &resolver,
+            &apiset_schema,
+        );
+
+        assert_eq!(missing_symbols.len(), 2);
+        assert_eq!(missing_symbols[0]["name"], "Baz");
+        assert!(
+            missing_symbols[0]["reason"]
+                .as_str()
+                .unwrap()
+                .starts_with("not exported by")
+        );
+        assert_eq!(missing_symbols[1]["ordinal"], 3);
+    }
+
+    #[test]
+    fn forwarders_are_followed_to_their_module() {
+        let (directory, resolver) = create_resolver(
+            "forwarders",
+            &[
+                (
+                    "a.dll",
+                    &[
+                        ("Foo", Some("b.Foo")),
+                        ("ByOrdinal", Some("b.#1")),
+                        ("Bar", Some("b.Bar")),
+                        ("Baz", Some("c.Baz")),
+                    ],
+                ),
+                ("b.dll", &[("Foo", None)]),
+            ],
+        );
+        let apiset_schema = super::super::apiset::APISet::new();
+        let a_path = directory.get_path().join("a.dll");
+
+        let foo = verify_symbol(&by_name("Foo"), &a_path, &resolver, &apiset_schema, 0);
+        let by_ordinal =
+            verify_symbol(&by_name("ByOrdinal"), &a_path, &resolver, &apiset_schema, 0);
+        let bar = verify_symbol(&by_name("Bar"), &a_path, &resolver, &apiset_schema, 0);
+        let baz = verify_symbol(&by_name("Baz"), &a_path, &resolver, &apiset_schema, 0);
+
+        assert!(foo.is_ok());
+        assert!(by_ordinal.is_ok());
+
+        let bar = bar.unwrap_err();
+
+        assert!(
+            bar.starts_with("forwarded to b.Bar, not exported by"),
+            "{bar}"
+        );
+        assert_eq!(
+            baz.unwrap_err(),
+            "forwarded to c.Baz, but c.dll cannot be found"
+        );
+    }
+
+    #[test]
+    fn forwarder_cycles_are_bounded() {
+        let (directory, resolver) =
+            create_resolver("cycles", &[("a.dll", &[("Loop", Some("a.Loop"))])]);
+        let apiset_schema = super::super::apiset::APISet::new();
+
+        let result = verify_symbol(
+            &by_name("Loop"),
+            &directory.get_path().join("a.dll"),
+            &resolver,
+            &apiset_schema,
+            0,
+        );
+
+        assert!(result.unwrap_err().ends_with("forwarder chain is too deep"));
+    }
+
+    #[test]
+    fn dlls_must_be_compatible_with_the_process_machine() {
+        use super::super::pe::Machine;
+
+        const MACHINES: [Machine; 6] = [
+            Machine::I386,
+            Machine::ArmNt,
+            Machine::Amd64,
+            Machine::Arm64,
+            Machine::Arm64Ec,
+            Machine::Arm64X,
+        ];
+
+        /* Rows are the machines of the process, columns the ones of the dlls, in MACHINES order */
+        const COMPATIBILITY: [[bool; 6]; 6] = [
+            [true, false, false, false, false, false],
+            [false, true, false, false, false, false],
+            [false, false, true, false, true, true],
+            [false, false, false, true, false, true],
+            [false, false, true, false, true, true],
+            [false, false, false, true, false, true],
+        ];
+
+        for (process_machine, row) in MACHINES.iter().zip(COMPATIBILITY) {
+            for (machine, compatible) in MACHINES.iter().zip(row) {
+                assert_eq!(
+                    is_machine_compatible(*process_machine, *machine),
+                    compatible,
+                    "{process_machine} process, {machine} dll"
+                );
+            }
+
+            assert!(!is_machine_compatible(
+                *process_machine,
+                Machine::Other(0x1c0)
+            ));
+        }
+
+        /* Without an executable, any dll goes */
+        for machine in MACHINES {
+            assert!(is_machine_compatible(Machine::Unknown, machine));
+        }
+    }
+
+    #[test]
+    fn wow64_redirects_the_system_directory() {
+        let directory = TestDirectory::new("wow64");
+        let windows_root = directory.create_dir("Windows");
+
+        directory.create_dir("Windows/system32");
+        directory.create_dir("Windows/SysWOW64");
+
+        let mut search_order = SearchOrder::default();
+        search_order.windows_root = Some(windows_root.clone());
+        search_order.wow64 = true;
+
+        let redirect = |path: &str| search_order.redirect_directory(&windows_root.join(path));
+
+        assert_eq!(redirect("system32"), windows_root.join("SysWOW64"));
+        assert_eq!(
+            redirect("SYSTEM32/downlevel"),
+            windows_root.join("SysWOW64/downlevel")
+        );
+        assert_eq!(
+            redirect("System32/drivers"),
+            windows_root.join("SysWOW64/drivers")
+        );
+        assert_eq!(
+            redirect("system32/catroot2x"),
+            windows_root.join("SysWOW64/catroot2x")
+        );
+
+        /* Sysnative only exists for 32-bit processes, to reach the native System32 */
+        assert_eq!(redirect("Sysnative"), windows_root.join("system32"));
+        assert_eq!(
+            redirect("sysnative/drivers"),
+            windows_root.join("system32/drivers")
+        );
+
+        for exempt_directory in [
+            "system32/catroot",
+            "system32/CatRoot2/{F750E6C3-38EE-11D1-85E5-00C04FC295EE}",
+            "system32/DriverStore",
+            "System32/drivers/etc",
+            "system32/LogFiles/WMI",
+            "system32/spool/drivers",
+        ] {
+            assert_eq!(
+                redirect(exempt_directory),
+                windows_root.join(exempt_directory)
+            );
+        }
+
+        assert_eq!(redirect("SysWOW64"), windows_root.join("SysWOW64"));
+        assert_eq!(
+            search_order.redirect_directory(&directory.get_path().join("system32")),
+            directory.get_path().join("system32")
+        );
+
+        /* 64-bit processes, and 32-bit Windows without SysWOW64, are not redirected */
+        search_order.wow64 = false;
+
+        assert_eq!(
+            search_order.redirect_directory(&windows_root.join("system32")),
+            windows_root.join("system32")
+        );
+
+        search_order.wow64 = true;
+        std::fs::remove_dir(windows_root.join("SysWOW64")).unwrap();
+
+        assert_eq!(
+            search_order.redirect_directory(&windows_root.join("system32")),
+            windows_root.join("system32")
+        );
+    }
+
+    #[test]
+    fn delay_loaded_dlls_are_flagged_in_the_output() {
+        let (directory, resolver) = create_resolver(
+            "delay-load",
+            &[("a.dll", &[("Foo", None)]), ("b.dll", &[("Bar", None)])],
+        );
+        let apiset_schema = super::super::apiset::APISet::new();
+
+        let mut image = ImageBuilder::new(IMAGE_FILE_MACHINE_AMD64);
+        image.add_imports(&[("a.dll", &[by_name("Foo")])]);
+        image.add_delay_imports(&[("B.DLL", &[by_name("Bar")])], true);
+
+        let app_path = directory.write("app.exe", image.build());
+
+        let dependencies =
+            get_dll_dependencies(&app_path, &resolver, &apiset_schema, false).unwrap();
+        let recursive_dependencies = get_dll_dependencies_recursive(
+            &app_path,
+            &resolver,
+            &apiset_schema,
+            &mut HashMap::new(),
+            &mut HashSet::new(),
+            false,
+        )
+        .unwrap();
+
+        for dependencies in [dependencies, recursive_dependencies] {
+            let dlls = &dependencies["dependencies"];
+
+            assert_eq!(dlls.len(), 2);
+            assert_eq!(dlls[0]["name"], "a.dll");
+            assert_eq!(dlls[0]["delay_load"], false);
+            assert_eq!(dlls[1]["name"], "b.dll");
+            assert_eq!(dlls[1]["delay_load"], true);
+            assert_eq!(
+                dlls[1]["path"],
+                directory.get_path().join("b.dll").to_str().unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn file_names_are_matched_exactly_ignoring_the_case() {
+        let directory = TestDirectory::new("file-names");
+        let user32_path = directory.write("user32.dll", "");
+        let myuser32_path = directory.write("myuser32.dll", "");
+        let shell32_path = directory.write("SHELL32.dll", "");
+
+        let index = DirectoryIndex::default();
+
+        assert_eq!(
+            index.find(directory.get_path(), "USER32.DLL"),
+            Some(user32_path.clone())
+        );
+        assert_eq!(
+            index.find(directory.get_path(), "shell32.dll"),
+            Some(shell32_path.clone())
+        );
+        assert_eq!(
+            index.find(directory.get_path(), "MyUser32.dll"),
+            Some(myuser32_path)
+        );
+        assert_eq!(index.find(directory.get_path(), "ser32.dll"), None);
+        assert_eq!(index.find(directory.get_path(), "user32"), None);
+
+        assert_eq!(
+            find_directory_entry(directory.get_path(), "USER32.DLL"),
+            Some(user32_path)
+        );
+        assert_eq!(
+            find_directory_entry(directory.get_path(), "shell32.dll"),
+            Some(shell32_path)
+        );
+        assert_eq!(
+            find_directory_entry(directory.get_path(), "ser32.dll"),
+            None
+        );
+
+        /* Only myuser32.dll is left, it must not be taken for user32.dll */
+        std::fs::remove_file(directory.get_path().join("user32.dll")).unwrap();
+
+        assert_eq!(
+            find_directory_entry(directory.get_path(), "user32.dll"),
+            None
+        );
+        assert_eq!(
+            DirectoryIndex::default().find(directory.get_path(), "user32.dll"),
+            None
+        );
+    }
+    #[test]
+    fn dot_local_directories_redirect_dlls() {
+        let (directory, mut resolver) = create_resolver(
+            "dot-local-directory",
+            &[("a.dll", &[("Foo", None)]), ("b.dll", &[("Foo", None)])],
+        );
+        let redirected_path = write_dll(&directory, "app/app.exe.local/a.dll");
+        let app_path = write_executable(&directory, "app/app.exe", &["a.dll", "B.DLL"]);
+
+        assert_eq!(
+            resolve_executable(&mut resolver, &app_path),
+            [
+                (
+                    path_string(&redirected_path),
+                    Some(path_string(&directory.get_path().join("app/app.exe.local")))
+                ),
+                (path_string(&directory.get_path().join("b.dll")), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn dot_local_files_redirect_dlls_to_the_application_directory() {
+        let (directory, mut resolver) = create_resolver(
+            "dot-local-file",
+            &[("a.dll", &[("Foo", None)]), ("b.dll", &[("Foo", None)])],
+        );
+        let dot_local_path = directory.write("app/app.exe.local", "");
+        let redirected_path = write_dll(&directory, "app/a.dll");
+        let app_path = write_executable(&directory, "app/app.exe", &["a.dll", "b.dll"]);
+
+        assert_eq!(
+            resolve_executable(&mut resolver, &app_path),
+            [
+                (
+                    path_string(&redirected_path),
+                    Some(path_string(&dot_local_path))
+                ),
+                (path_string(&directory.get_path().join("b.dll")), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn known_dlls_are_never_redirected() {
+        let (directory, mut resolver) = create_resolver(
+            "known-dlls",
+            &[("a.dll", &[("Foo", None)]), ("b.dll", &[("Foo", None)])],
+        );
+        let known_dll_path = write_dll(&directory, "Windows/System32/a.dll");
+        let redirected_path = write_dll(&directory, "app/app.exe.local/b.dll");
+        let app_path = write_executable(&directory, "app/app.exe", &["a.dll", "b.dll"]);
+
+        write_dll(&directory, "app/app.exe.local/a.dll");
+
+        resolver.known_dlls = super::super::knowndlls::KnownDlls::from_names(["A.dll"]);
+        resolver.system32_directory = Some(directory.get_path().join("Windows/System32"));
+
+        assert_eq!(
+            resolve_executable(&mut resolver, &app_path),
+            [
+                (path_string(&known_dll_path), None),
+                (
+                    path_string(&redirected_path),
+                    Some(path_string(&directory.get_path().join("app/app.exe.local")))
+                ),
+            ]
+        );
+
+        let apiset_schema = super::super::apiset::APISet::new();
+        let resolved_dll = resolve_dll("a.dll", "app.exe", None, &resolver, &apiset_schema)
+            .ok()
+            .unwrap();
+
+        assert!(resolved_dll.known_dll);
+        assert_eq!(
+            resolved_dll.shadowed_path,
+            Some(path_string(&directory.get_path().join("a.dll")))
+        );
+    }
+
+    #[test]
+    fn private_paths_are_probed_inside_the_application_directory() {
+        const MANIFEST: &str = r#"<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+  <dependency>
+    <dependentAssembly>
+      <assemblyIdentity type="win32" name="Contoso.Lib" version="1.0.0.0" processorArchitecture="amd64"/>
+    </dependentAssembly>
+  </dependency>
+</assembly>"#;
+        const ASSEMBLY_MANIFEST: &str = r#"<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0">
+  <assemblyIdentity type="win32" name="Contoso.Lib" version="1.0.0.0" processorArchitecture="amd64"/>
+  <file name="c.dll"/>
+</assembly>"#;
+
+        let configuration = |private_path: &str| {
+            format!(
+                r#"<configuration>
+  <windows>
+    <assemblyBinding xmlns="urn:schemas-microsoft-com:asm.v1">
+      <probing privatePath="{private_path}"/>
+    </assemblyBinding>
+  </windows>
+</configuration>"#
+            )
+        };
+
+        let (directory, mut resolver) = create_resolver("private-paths", &[]);
+
+        let app_path = write_executable(&directory, "app/app.exe", &["c.dll"]);
+        let configuration_path = directory.write("app/app.exe.config", configuration("bin\\lib"));
+        let assembly_dll_path = write_dll(&directory, "app/bin/lib/c.dll");
+
+        directory.write("app/app.exe.manifest", MANIFEST);
+        directory.write("app/bin/lib/Contoso.Lib.manifest", ASSEMBLY_MANIFEST);
+
+        assert_eq!(
+            resolve_executable(&mut resolver, &app_path),
+            [(
+                path_string(&assembly_dll_path),
+                Some(path_string(&configuration_path))
+            )]
+        );
+
+        /* The assembly is outside the application directory, the private path is refused */
+        let app_path = write_executable(&directory, "other/app.exe", &["c.dll"]);
+
+        directory.write("other/app.exe.config", configuration("..\\shared"));
+        directory.write("other/app.exe.manifest", MANIFEST);
+        directory.write("shared/Contoso.Lib.manifest", ASSEMBLY_MANIFEST);
+        write_dll(&directory, "shared/c.dll");
+
+        assert_eq!(
+            resolve_executable(&mut resolver, &app_path),
+            [("<unknown>".to_string(), None)]
+        );
     }
 }
@@ -0,0 +1,781 @@
+use std::cell::OnceCell;
+use std::path::{Path, PathBuf};
+
+use super::manifest::{AssemblyIdentity, Manifest};
+use super::pe::Machine;
+
+/*
+ * Processor architecture of the process loading a PE, as used in manifests. Dependencies on "*"
+ * bind to the assemblies of this architecture.
+ *
+ * ARM64EC code runs alongside x64 code and loads x64 dlls, so it binds to the amd64 assemblies
+ * Windows on ARM ships for emulated processes. ARM64X images run as native ARM64 processes
+ */
+pub fn get_processor_architecture(pe: &super::pe::PE) -> &'static str {
+    match pe.get_machine() {
+        Machine::I386 => return "x86",
+        Machine::ArmNt => return "arm",
+        Machine::Amd64 | Machine::Arm64Ec => return "amd64",
+        Machine::Arm64 | Machine::Arm64X => return "arm64",
+        Machine::Unknown | Machine::Other(_) => {
+            if pe.is_32_bits() {
+                return "x86";
+            }
+
+            return "amd64";
+        }
+    }
+}
+
+/*
+ * Side-by-side assemblies
+ * https://learn.microsoft.com/en-us/windows/win32/sbscs/assembly-searching-sequence
+ */
+
+/*
+ * Assembly found for a dependency of a manifest, either in WinSxS or next to the application
+ * (private assembly)
+ */
+#[derive(Clone, Debug)]
+pub struct Assembly {
+    pub identity: AssemblyIdentity,
+    pub directory: PathBuf,
+    pub files: Vec<String>,
+    pub private: bool,
+    /* Application configuration file that redirected the reference or located the assembly */
+    pub redirection: Option<PathBuf>,
+}
+
+impl Assembly {
+    /*
+     * Finds a file of the assembly. When the manifest of the assembly cannot be read, any file of
+     * its directory is considered to be part of it
+     */
+    pub fn find_file(&self, name: &str) -> Option<PathBuf> {
+        if !self.files.is_empty()
+            && !self
+                .files
+                .iter()
+                .any(|file| file.eq_ignore_ascii_case(name))
+        {
+            return None;
+        }
+
+        return super::search::find_directory_entry(&self.directory, name)
+            .filter(|file_path| file_path.is_file());
+    }
+}
+
+/*
+ * Where the assemblies of an application are probed: next to it and in the privatePath
+ * directories of its configuration file (<app>.exe.config), whose binding redirects and publisher
+ * policy setting also apply
+ */
+#[derive(Default, Clone, Debug)]
+pub struct AssemblyProbing {
+    pub application_directory: Option<PathBuf>,
+    pub processor_architecture: String,
+    pub configuration: Option<Manifest>,
+    pub configuration_path: Option<PathBuf>,
+}
+
+impl AssemblyProbing {
+    pub fn new(
+        application_directory: Option<&Path>,
+        processor_architecture: &str,
+    ) -> AssemblyProbing {
+        return AssemblyProbing {
+            application_directory: application_directory.map(Path::to_path_buf),
+            processor_architecture: processor_architecture.to_string(),
+            configuration: None,
+            configuration_path: None,
+        };
+    }
+
+    pub fn load_configuration(&mut self, configuration_path: &Path) {
+        if let Some(configuration) = Manifest::from_file(configuration_path) {
+            log::info!(
+                "Using application configuration file: {}",
+                configuration_path.display()
+            );
+
+            self.configuration = Some(configuration);
+            self.configuration_path = Some(configuration_path.to_path_buf());
+        }
+    }
+
+    /*
+     * Private paths are relative to the application directory and cannot leave it
+     */
+    fn get_private_directories(&self) -> Vec<PathBuf> {
+        let (Some(application_directory), Some(configuration)) =
+            (&self.application_directory, &self.configuration)
+        else {
+            return Vec::new();
+        };
+
+        let mut private_directories: Vec<PathBuf> = Vec::new();
+
+        for private_path in &configuration.private_paths {
+            let mut directory = Some(application_directory.clone());
+
+            for component in private_path.split(['\\', '/']) {
+                if component.is_empty() || component == "." {
+                    continue;
+                }
+
+                directory = match component {
+                    ".." => None,
+                    _ => directory.and_then(|directory| {
+                        super::search::find_directory_entry(&directory, component)
+                    }),
+                };
+            }
+
+            match directory {
+                Some(directory) if directory.is_dir() => private_directories.push(directory),
+                _ => log::debug!("Ignoring private path: {private_path}"),
+            }
+        }
+
+        return private_directories;
+    }
+
+    fn is_publisher_policy_disabled(&self) -> bool {
+        return self
+            .configuration
+            .as_ref()
+            .is_some_and(|configuration| configuration.publisher_policy_disabled);
+    }
+}
+
+/*
+ * Directory of the WinSxS store, named <arch>_<name>_<public key token>_<version>_<language>_<hash>
+ */
+#[derive(Clone, Debug)]
+struct WinSxSEntry {
+    identity: AssemblyIdentity,
+    directory_name: String,
+    path: PathBuf,
+}
+
+impl WinSxSEntry {
+    fn from_directory_name(path: PathBuf, directory_name: &str) -> Option<WinSxSEntry> {
+        let (processor_architecture, rest) = directory_name.split_once('_')?;
+
+        /* Names can contain underscores, so the other components are split from the end */
+        let mut components = rest.rsplitn(5, '_');
+
+        let _hash = components.next()?;
+        let language = components.next()?;
+        let version = components.next()?;
+        let public_key_token = components.next()?;
+        let name = components.next()?;
+
+        let mut identity = AssemblyIdentity::new();
+        identity.name = name.to_string();
+        identity.version = version.to_string();
+        identity.processor_architecture = processor_architecture.to_string();
+        identity.public_key_token = public_key_token.to_string();
+        identity.language = language.to_string();
+
+        return Some(WinSxSEntry {
+            identity,
+            directory_name: directory_name.to_string(),
+            path,
+        });
+    }
+
+    /*
+     * Long names are shortened in directory names by eliding their middle part with "..", e.g.
+     * microsoft-windows-a..ence-infrastructure
+     */
+    fn matches_name(&self, name: &str) -> bool {
+        let name = name.to_ascii_lowercase();
+        let entry_name = self.identity.name.to_ascii_lowercase();
+
+        return match entry_name.split_once("..") {
+            Some((prefix, suffix)) => {
+                name.len() >= prefix.len() + suffix.len()
+                    && name.starts_with(prefix)
+                    && name.ends_with(suffix)
+            }
+            None => name == entry_name,
+        };
+    }
+
+    /*
+     * The name was checked with matches_name() so it is not compared again, it can be shortened
+     */
+    fn matches(&self, reference: &AssemblyIdentity) -> bool {
+        let mut identity = self.identity.clone();
+        identity.name = reference.name.clone();
+
+        return self.matches_name(&reference.name) && identity.matches(reference);
+    }
+}
+
+fn read_winsxs_entries(winsxs_directory: &Path) -> Vec<WinSxSEntry> {
+    log::trace!("Indexing WinSxS directory: {}", winsxs_directory.display());
+
+    let mut entries: Vec<WinSxSEntry> = Vec::new();
+
+    let directory_entries = match std::fs::read_dir(winsxs_directory) {
+        Ok(e) => e,
+        Err(err) => {
+            log::warn!(
+                "Cannot read WinSxS directory: {} ({})",
+                winsxs_directory.display(),
+                err
+            );
+            return entries;
+        }
+    };
+
+    for entry in directory_entries.flatten() {
+        if !entry.path().is_dir() {
+            continue;
+        }
+
+        if let Some(directory_name) = entry.file_name().to_str()
+            && let Some(winsxs_entry) =
+                WinSxSEntry::from_directory_name(entry.path(), directory_name)
+        {
+            entries.push(winsxs_entry);
+        }
+    }
+
+    entries.sort_by(|a, b| a.directory_name.cmp(&b.directory_name));
+
+    return entries;
+}
+
+/*
+ * Finds the assemblies manifests depend on. The WinSxS directory is indexed once, the first time an
+ * assembly is looked for
+ */
+#[derive(Default, Debug)]
+pub struct AssemblyResolver {
+    winsxs_directory: Option<PathBuf>,
+    winsxs_entries: OnceCell<Vec<WinSxSEntry>>,
+}
+
+impl AssemblyResolver {
+    pub fn new(winsxs_directory: Option<PathBuf>) -> AssemblyResolver {
+        return AssemblyResolver {
+            winsxs_directory,
+            winsxs_entries: OnceCell::new(),
+        };
+    }
+
+    fn get_winsxs_entries(&self) -> &[WinSxSEntry] {
+        return self
+            .winsxs_entries
+            .get_or_init(|| match &self.winsxs_directory {
+                Some(winsxs_directory) => read_winsxs_entries(winsxs_directory),
+                None => Vec::new(),
+            });
+    }
+
+    /*
+     * Manifests of the store are in its Manifests directory, named after the directory of the
+     * assembly. Older stores keep them (or the .policy files of publisher policies) in the
+     * directory of the assembly itself
+     */
+    fn read_winsxs_manifest(&self, entry: &WinSxSEntry) -> Option<Manifest> {
+        let manifest = self
+            .winsxs_directory
+            .as_ref()
+            .and_then(|winsxs_directory| {
+                super::search::find_directory_entry(winsxs_directory, "Manifests")
+            })
+            .and_then(|manifests_directory| {
+                Manifest::from_file(
+                    &manifests_directory.join(format!("{}.manifest", entry.directory_name)),
+                )
+            });
+
+        if manifest.is_some() {
+            return manifest;
+        }
+
+        let mut manifest_paths: Vec<PathBuf> = std::fs::read_dir(&entry.path)
+            .ok()?
+            .flatten()
+            .map(|directory_entry| directory_entry.path())
+            .filter(|file_path| {
+                file_path.extension().is_some_and(|extension| {
+                    extension.eq_ignore_ascii_case("manifest")
+                        || extension.eq_ignore_ascii_case("policy")
+                })
+            })
+            .collect();
+
+        manifest_paths.sort();
+
+        return manifest_paths
+            .first()
+            .and_then(|manifest_path| Manifest::from_file(manifest_path));
+    }
+
+    /*
+     * Publisher policies are assemblies named policy.<major>.<minor>.<name>, redirecting the
+     * versions of the assembly with the same major and minor numbers. The policy with the highest
+     * version applies
+     */
+    fn apply_publisher_policy(&self, reference: &AssemblyIdentity) -> Option<AssemblyIdentity> {
+        let version = super::manifest::parse_assembly_version(&reference.version)?;
+
+        let mut policy_reference = reference.clone();
+        policy_reference.name = format!("policy.{}.{}.{}", version[0], version[1], reference.name);
+        policy_reference.version = String::new();
+        policy_reference.language = "*".to_string();
+
+        let policy_entry = self
+            .get_winsxs_entries()
+            .iter()
+            .filter(|entry| entry.matches(&policy_reference))
+            .max_by_key(|entry| super::manifest::parse_assembly_version(&entry.identity.version))?;
+
+        let redirected_reference = self
+            .read_winsxs_manifest(policy_entry)?
+            .redirect(reference)?;
+
+        log::debug!(
+            "Publisher policy {} redirects {} to version {}",
+            policy_entry.directory_name,
+            reference,
+            redirected_reference.version
+        );
+
+        return Some(redirected_reference);
+    }
+
+    /*
+     * Shared assemblies must match the reference exactly, after publisher policies are applied.
+     * Neutral assemblies are preferred when any language is accepted
+     */
+    fn find_in_winsxs(
+        &self,
+        reference: &AssemblyIdentity,
+        publisher_policy: bool,
+    ) -> Option<Assembly> {
+        let reference = match publisher_policy {
+            true => self
+                .apply_publisher_policy(reference)
+                .unwrap_or_else(|| reference.clone()),
+            false => reference.clone(),
+        };
+
+        let mut entries = self
+            .get_winsxs_entries()
+            .iter()
+            .filter(|entry| entry.matches(&reference));
+
+        let first_entry = entries.next()?;
+        let entry = match first_entry.identity.is_neutral() {
+            true => first_entry,
+            false => entries
+                .find(|entry| entry.identity.is_neutral())
+                .unwrap_or(first_entry),
+        };
+
+        let manifest = self.read_winsxs_manifest(entry);
+
+        /* The directory name is lowercase and possibly shortened, the manifest has the identity */
+        let identity = manifest
+            .as_ref()
+            .and_then(|manifest| manifest.identity.clone())
+            .unwrap_or_else(|| entry.identity.clone());
+
+        return Some(Assembly {
+            identity,
+            directory: entry.path.clone(),
+            files: manifest.map(|manifest| manifest.files).unwrap_or_default(),
+            private: false,
+            redirection: None,
+        });
+    }
+
+    /*
+     * Private assemblies are probed in the application directory, as <name>.dll (manifest in the
+     * resources) or <name>.manifest, then in a subdirectory named after the assembly, and the same
+     * way in the private paths of the configuration. Localized assemblies are probed in a
+     * subdirectory named after their language first
+     */
+    fn find_private_assembly(
+        &self,
+        reference: &AssemblyIdentity,
+        probing: &AssemblyProbing,
+    ) -> Option<Assembly> {
+        let mut root_directories: Vec<(PathBuf, bool)> = Vec::new();
+
+        if let Some(application_directory) = &probing.application_directory {
+            root_directories.push((application_directory.clone(), false));
+        }
+
+        for private_directory in probing.get_private_directories() {
+            root_directories.push((private_directory, true));
+        }
+
+        let mut probed_directories: Vec<(PathBuf, bool)> = Vec::new();
+
+        for (root_directory, is_private_path) in root_directories {
+            let mut base_directories: Vec<PathBuf> = Vec::new();
+
+            if !reference.is_neutral()
+                && reference.language != "*"
+                && let Some(language_directory) =
+                    super::search::find_directory_entry(&root_directory, &reference.language)
+            {
+                base_directories.push(language_directory);
+            }
+
+            base_directories.push(root_directory);
+
+            for base_directory in base_directories {
+                let assembly_directory =
+                    super::search::find_directory_entry(&base_directory, &reference.name)
+                        .filter(|directory| directory.is_dir());
+
+                probed_directories.push((base_directory, is_private_path));
+
+                if let Some(assembly_directory) = assembly_directory {
+                    probed_directories.push((assembly_directory, is_private_path));
+                }
+            }
+        }
+
+        for (directory, is_private_path) in probed_directories {
+            for extension in ["dll", "manifest"] {
+                let manifest_path = match super::search::find_directory_entry(
+                    &directory,
+                    &format!("{}.{}", reference.name, extension),
+                ) {
+                    Some(manifest_path) => manifest_path,
+                    None => continue,
+                };
+
+                let manifest = match extension {
+                    "dll" => super::pe::parse_pe(&manifest_path)
+                        .ok()
+                        .and_then(|pe| super::manifest::get_assembly_manifest(&pe)),
+                    _ => Manifest::from_file(&manifest_path),
+                };
+
+                if let Some(manifest) = manifest
+                    && let Some(manifest_identity) = &manifest.identity
+                {
+                    if !manifest_identity.matches(reference) {
+                        log::debug!(
+                            "Private assembly {} does not match the reference to {}",
+                            manifest_path.display(),
+                            reference
+                        );
+                        continue;
+                    }
+
+                    return Some(Assembly {
+                        identity: manifest_identity.clone(),
+                        directory,
+                        files: manifest.files,
+                        private: true,
+                        redirection: match is_private_path {
+                            true => probing.configuration_path.clone(),
+                            false => None,
+                        },
+                    });
+                }
+            }
+        }
+
+        return None;
+    }
+
+    /*
+     * Follows the assembly searching sequence: shared assemblies in WinSxS first, then private
+     * assemblies next to the application. References to any processor architecture bind to the
+     * architecture of the process, and the binding redirects of the application configuration
+     * apply before publisher policies
+     */
+    pub fn find_assembly(
+        &self,
+        reference: &AssemblyIdentity,
+        probing: &AssemblyProbing,
+    ) -> Option<Assembly> {
+        let mut reference = reference.clone();
+        let mut redirection: Option<PathBuf> = None;
+
+        if reference.processor_architecture == "*" {
+            reference.processor_architecture = probing.processor_architecture.clone();
+        }
+
+        if let Some(configuration) = &probing.configuration
+            && let Some(redirected_reference) = configuration.redirect(&reference)
+        {
+            log::debug!(
+                "Application configuration redirects {} to version {}",
+                reference,
+                redirected_reference.version
+            );
+
+            reference = redirected_reference;
+            redirection = probing.configuration_path.clone();
+        }
+
+        let mut assembly =
+            match self.find_in_winsxs(&reference, !probing.is_publisher_policy_disabled()) {
+                Some(assembly) => assembly,
+                None => self.find_private_assembly(&reference, probing)?,
+            };
+
+        if assembly.redirection.is_none() {
+            assembly.redirection = redirection;
+        }
+
+        return Some(assembly);
+    }
+}
+
+/*
+ * Assemblies a module depends on through its manifest, dlls being looked for in them before the
+ * search order
+ */
+#[derive(Default, Clone, Debug)]
+pub struct ActivationContext {
+    pub dependencies: Vec<(AssemblyIdentity, Option<Assembly>)>,
+}
+
+impl ActivationContext {
+    pub fn new() -> ActivationContext {
+        return ActivationContext::default();
+    }
+
+    pub fn from_manifest(
+        manifest: &Manifest,
+        probing: &AssemblyProbing,
+        assembly_resolver: &AssemblyResolver,
+    ) -> ActivationContext {
+        let mut activation_context = ActivationContext::new();
+
+        for identity in &manifest.dependencies {
+            let assembly = assembly_resolver.find_assembly(identity, probing);
+
+            if assembly.is_none() {
+                log::warn!("Cannot find side-by-side assembly {identity}");
+            }
+
+            activation_context
+                .dependencies
+                .push((identity.clone(), assembly));
+        }
+
+        return activation_context;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.dependencies.is_empty();
+    }
+
+    pub fn find_dll(&self, name: &str) -> Option<(PathBuf, &Assembly)> {
+        for (_, assembly) in &self.dependencies {
+            if let Some(assembly) = assembly
+                && let Some(file_path) = assembly.find_file(name)
+            {
+                return Some((file_path, assembly));
+            }
+        }
+
+        return None;
+    }
+
+    pub fn to_json(&self) -> json::JsonValue {
+        let mut assemblies: Vec<json::JsonValue> = Vec::new();
+
+        for (identity, assembly) in &self.dependencies {
+            let mut assembly_object = identity.to_json();
+
+            match assembly {
+                Some(assembly) => {
+                    assembly_object["path"] = assembly
+                        .directory
+                        .to_str()
+                        .unwrap_or("<invalid path>")
+                        .into();
+                    assembly_object["private"] = assembly.private.into();
+
+                    /* Version the reference was redirected to, by a publisher policy or the configuration */
+                    if assembly.identity.version != identity.version {
+                        assembly_object["resolved_version"] =
+                            assembly.identity.version.as_str().into();
+                    }
+
+                    if let Some(redirection) = &assembly.redirection {
+                        assembly_object["redirection"] =
+                            redirection.to_str().unwrap_or("<invalid path>").into();
+                    }
+                }
+                None => assembly_object["path"] = "<unknown>".into(),
+            }
+
+            assemblies.push(assembly_object);
+        }
+
+        return json::JsonValue::Array(assemblies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::pe::test_image::{ImageBuilder, LOAD_CONFIG_DIRECTORY};
+    use super::super::pe::{
+        self, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64, IMAGE_FILE_MACHINE_I386,
+    };
+    use super::super::test_directory::TestDirectory;
+    use super::*;
+
+    fn winsxs_entry(directory_name: &str) -> Option<WinSxSEntry> {
+        return WinSxSEntry::from_directory_name(PathBuf::from(directory_name), directory_name);
+    }
+
+    fn reference(name: &str, version: &str, processor_architecture: &str) -> AssemblyIdentity {
+        let mut reference = AssemblyIdentity::new();
+        reference.name = name.to_string();
+        reference.version = version.to_string();
+        reference.processor_architecture = processor_architecture.to_string();
+        reference.public_key_token = "1fc8b3b9a1e18e3b".to_string();
+
+        return reference;
+    }
+
+    #[test]
+    fn winsxs_directory_names_are_split_into_identities() {
+        let entry = winsxs_entry(
+            "amd64_microsoft.windows.common-controls_6595b64144ccf1df_6.0.19041.1_none_a8625c1886757984",
+        )
+        .unwrap();
+
+        assert_eq!(entry.identity.processor_architecture, "amd64");
+        assert_eq!(entry.identity.name, "microsoft.windows.common-controls");
+        assert_eq!(entry.identity.public_key_token, "6595b64144ccf1df");
+        assert_eq!(entry.identity.version, "6.0.19041.1");
+        assert_eq!(entry.identity.language, "none");
+
+        let entry =
+            winsxs_entry("wow64_my_assembly_name_1fc8b3b9a1e18e3b_1.0.0.0_en-us_1234").unwrap();
+
+        assert_eq!(entry.identity.processor_architecture, "wow64");
+        assert_eq!(entry.identity.name, "my_assembly_name");
+        assert_eq!(entry.identity.language, "en-us");
+
+        assert!(winsxs_entry("amd64_name_1.0.0.0_none_1234").is_none());
+        assert!(winsxs_entry("Manifests").is_none());
+    }
+
+    #[test]
+    fn shortened_winsxs_names_match_the_full_name() {
+        let entry = winsxs_entry(
+            "amd64_microsoft-windows-a..ence-infrastructure_31bf3856ad364e35_10.0.19041.1_none_1234",
+        )
+        .unwrap();
+
+        assert!(entry.matches_name("Microsoft-Windows-Application-Experience-Infrastructure"));
+        assert!(!entry.matches_name("microsoft-windows-application-infrastructure"));
+        assert!(!entry.matches_name("microsoft-windows-shell-experience-infrastructure"));
+
+        let entry = winsxs_entry("amd64_microsoft.vc90.crt_1fc8b3b9a1e18e3b_9.0.21022.8_none_1234")
+            .unwrap();
+
+        assert!(entry.matches_name("Microsoft.VC90.CRT"));
+        assert!(!entry.matches_name("Microsoft.VC90.CRT2"));
+    }
+
+    #[test]
+    fn the_highest_publisher_policy_applies() {
+        let directory = TestDirectory::new("winsxs");
+
+        /* The versions of the policies have to be compared as numbers, not as strings */
+        for (policy_name, version) in [
+            ("policy.9.0.microsoft.vc90.crt", "9.0.30729.9"),
+            ("policy.9.0.microsoft.vc90.crt", "9.0.30729.10"),
+            ("policy.8.0.microsoft.vc90.crt", "9.0.30729.11"),
+        ] {
+            let directory_name =
+                format!("amd64_{policy_name}_1fc8b3b9a1e18e3b_{version}_none_1234");
+
+            directory.create_dir(&directory_name);
+            directory.write(
+                &format!("Manifests/{directory_name}.manifest"),
+                format!(
+                    r#"<assembly>
+                      <assemblyIdentity type="win32-policy" name="{policy_name}" version="{version}"/>
+                      <dependency><dependentAssembly>
+                        <assemblyIdentity type="win32" name="Microsoft.VC90.CRT"/>
+                        <bindingRedirect oldVersion="8.0.0.0-9.0.30729.11" newVersion="{version}"/>
+                      </dependentAssembly></dependency>
+                    </assembly>"#
+                ),
+            );
+        }
+
+        let resolver = AssemblyResolver::new(Some(directory.get_path().to_path_buf()));
+
+        let redirected_reference = resolver.apply_publisher_policy(&reference(
+            "Microsoft.VC90.CRT",
+            "9.0.21022.8",
+            "amd64",
+        ));
+        let other_architecture =
+            resolver.apply_publisher_policy(&reference("Microsoft.VC90.CRT", "9.0.21022.8", "x86"));
+        let other_minor =
+            resolver.apply_publisher_policy(&reference("Microsoft.VC90.CRT", "9.1.0.0", "amd64"));
+
+        assert_eq!(redirected_reference.unwrap().version, "9.0.30729.10");
+        assert!(other_architecture.is_none());
+        assert!(other_minor.is_none());
+    }
+
+    /* Image of the machine, hybrid when it has CHPE metadata */
+    fn processor_architecture(machine: u16, hybrid: bool) -> &'static str {
+        let mut image = ImageBuilder::new(machine);
+
+        if hybrid {
+            let mut load_config = vec![0u8; 0xd0];
+            load_config[0..4].copy_from_slice(&0xd0u32.to_le_bytes());
+            load_config[0xc8..0xd0].copy_from_slice(&0x180012000u64.to_le_bytes());
+
+            let rva = image.append(&load_config);
+            image.set_directory(LOAD_CONFIG_DIRECTORY, rva, 0xd0);
+        }
+
+        let pe = pe::parse_pe_data(pe::PEData::Owned(image.build())).unwrap();
+
+        return get_processor_architecture(&pe);
+    }
+
+    #[test]
+    fn processor_architectures_follow_the_machine_of_the_image() {
+        assert_eq!(
+            processor_architecture(IMAGE_FILE_MACHINE_I386, false),
+            "x86"
+        );
+        assert_eq!(
+            processor_architecture(IMAGE_FILE_MACHINE_AMD64, false),
+            "amd64"
+        );
+        assert_eq!(
+            processor_architecture(IMAGE_FILE_MACHINE_ARM64, false),
+            "arm64"
+        );
+
+        /* ARM64EC binds to the x64 assemblies, ARM64X to the native ones */
+        assert_eq!(
+            processor_architecture(IMAGE_FILE_MACHINE_AMD64, true),
+            "amd64"
+        );
+        assert_eq!(
+            processor_architecture(IMAGE_FILE_MACHINE_ARM64, true),
+            "arm64"
+        );
+    }
+}
@@ -0,0 +1,50 @@
+use std::path::{Path, PathBuf};
+
+/*
+ * Temporary directory of a test, removed with its content when dropped, including when an
+ * assertion fails
+ */
+pub struct TestDirectory {
+    path: PathBuf,
+}
+
+impl TestDirectory {
+    pub fn new(test_name: &str) -> TestDirectory {
+        let path = std::env::temp_dir().join(format!("fdw-{test_name}-{}", std::process::id()));
+
+        /* Left over by a test that was killed */
+        let _ = std::fs::remove_dir_all(&path);
+
+        std::fs::create_dir_all(&path).unwrap();
+
+        return TestDirectory { path };
+    }
+
+    pub fn get_path(&self) -> &Path {
+        return &self.path;
+    }
+
+    /* Writes a file, creating its parent directories */
+    pub fn write(&self, relative_path: &str, data: impl AsRef<[u8]>) -> PathBuf {
+        let file_path = self.path.join(relative_path);
+
+        std::fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+        std::fs::write(&file_path, data).unwrap();
+
+        return file_path;
+    }
+
+    pub fn create_dir(&self, relative_path: &str) -> PathBuf {
+        let directory = self.path.join(relative_path);
+
+        std::fs::create_dir_all(&directory).unwrap();
+
+        return directory;
+    }
+}
+
+impl Drop for TestDirectory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}